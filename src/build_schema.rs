@@ -1,12 +1,11 @@
 use std::{
-    collections::HashMap,
     sync::{Arc, Mutex},
     thread::Result,
 };
 
 use cynic::{http::ReqwestExt, QueryBuilder};
 use cynic_introspection::{
-    Directive, DirectiveLocation, FieldWrapping, InterfaceType, IntrospectionQuery, Type,
+    Directive, DirectiveLocation, FieldWrapping, InterfaceType, IntrospectionQuery, Schema, Type,
     UnionType, WrappingType,
 };
 use stringcase::{kebab_case, pascal_case};
@@ -14,8 +13,8 @@ use tokio::task::spawn_blocking;
 
 use crate::{
     config::{parse_outside_types::OutsideTypes, workspace::WorkspaceConfig},
-    enums::generate_enum::generate_enum,
-    hasura_types::as_gql_field,
+    enums::{generate_enum::generate_enum, postgres_types::PursTypes, shared_enums::SharedEnums},
+    hasura_types::{as_gql_argument, as_gql_field, SchemaTypes},
     purescript_gen::{
         purescript_argument::Argument,
         purescript_import::PurescriptImport,
@@ -28,13 +27,8 @@ use crate::{
     write::write,
 };
 
-pub async fn build_schema(
-    role: String,
-    postgres_types: Arc<Mutex<HashMap<String, (String, String, String)>>>,
-    outside_types: Arc<Mutex<OutsideTypes>>,
-    workspace_config: WorkspaceConfig,
-) -> Result<()> {
-    // Fetch the introspection schema
+/// Fetches the GraphQL schema a role can see
+pub async fn fetch_schema(role: String) -> Schema {
     let graphql_url = std::env::var("GRAPHQL_URL").expect("GRAPHQL_URL must be set");
     let hasura_url = std::env::var("HASURA_URL");
     let graphql_url = hasura_url.unwrap_or(graphql_url);
@@ -49,9 +43,21 @@ pub async fn build_schema(
         .data
         .expect("Failed to parse GraphQL introspection schema.");
 
-    let schema = introspection_data
+    introspection_data
         .into_schema()
-        .expect("Failed to convert introspection data.");
+        .expect("Failed to convert introspection data.")
+}
+
+pub async fn build_schema(
+    role: String,
+    schema: Schema,
+    postgres_types: Arc<Mutex<PursTypes>>,
+    outside_types: Arc<Mutex<OutsideTypes>>,
+    shared_enums: Arc<SharedEnums>,
+    workspace_config: WorkspaceConfig,
+) -> Result<()> {
+    // Collect the tables and scalars in this schema, which fields are resolved against
+    let schema_types = SchemaTypes::new(&schema);
 
     // Create the purescript types;
     let mut records: Vec<PurescriptRecord> = vec![];
@@ -151,13 +157,14 @@ pub async fn build_schema(
                     let mut args = PurescriptRecord::new("Arguments");
                     for arg in &field.args {
                         let arg_type = wrap_type(
-                            as_gql_field(
+                            as_gql_argument(
                                 &field.name,
                                 &arg.name,
                                 &arg.ty.name,
                                 &mut imports,
                                 &postgres_types,
                                 &outside_types,
+                                &schema_types,
                             ),
                             &arg.ty.wrapping,
                             &mut imports,
@@ -178,6 +185,7 @@ pub async fn build_schema(
                             &mut imports,
                             &postgres_types,
                             &outside_types,
+                            &schema_types,
                         ),
                         &field.ty.wrapping,
                         &mut imports,
@@ -219,13 +227,33 @@ pub async fn build_schema(
                 if en.name.starts_with("__") {
                     continue;
                 }
+                // Enums generated from the database, such as enum tables, are shared by every role
+                if postgres_types
+                    .lock()
+                    .expect("Failed to lock purs type to thread.")
+                    .contains_key(&en.name)
+                {
+                    continue;
+                }
 
                 // Generate purescript enums for all graphql types
                 // These include table select columns as well as custom enums
-                let enum_to_add = generate_enum(&en, &mut imports, &workspace_config).await;
+                let enum_to_add = generate_enum(en, &shared_enums, &mut imports).await;
                 if let Some(variant) = enum_to_add {
                     add_import("prelude", "Prelude", "Unit", &mut imports);
                     add_import("variant", "Data.Variant", "Variant", &mut imports);
+                    // Used by the variant's helpers
+                    add_import("prelude", "Prelude", "unit", &mut imports);
+                    add_import("prelude", "Prelude", "(#)", &mut imports);
+                    add_import("variant", "Data.Variant", "inj", &mut imports);
+                    add_import("variant", "Data.Variant", "case_", &mut imports);
+                    add_import("variant", "Data.Variant", "on", &mut imports);
+                    add_import("maybe", "Data.Maybe", "Maybe(..)", &mut imports);
+                    add_import("prelude", "Type.Proxy", "Proxy(..)", &mut imports);
+                    if variant.has_row() {
+                        add_import("prelude", "Prim.Row", "class Cons", &mut imports);
+                        add_import("prelude", "Data.Symbol", "class IsSymbol", &mut imports);
+                    }
                     variants.push(variant);
                 }
             }
@@ -251,6 +279,7 @@ pub async fn build_schema(
                             &mut imports,
                             &postgres_types,
                             &outside_types,
+                            &schema_types,
                         ),
                         &field.ty.wrapping,
                         &mut imports,
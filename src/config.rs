@@ -1,3 +1,8 @@
+pub mod glob;
+pub mod mock_outside_types;
 pub mod parse_outside_types;
 pub mod parse_roles;
+pub mod postgres_connection;
+pub mod validate_outside_types;
 pub mod workspace;
+pub mod yaml_positions;
@@ -0,0 +1,102 @@
+/// Matches `text` against a glob `pattern` where `*` matches any run of characters.
+/// Returns the text matched by each `*` in order, or None if the pattern doesn't match.
+pub fn glob_captures(pattern: &str, text: &str) -> Option<Vec<String>> {
+    let (literal, rest) = match pattern.split_once('*') {
+        None => return (pattern == text).then(Vec::new),
+        Some(split) => split,
+    };
+    let text = text.strip_prefix(literal)?;
+
+    // Try the shortest capture first so earlier wildcards match as little as possible
+    for end in (0..=text.len()).filter(|i| text.is_char_boundary(*i)) {
+        if let Some(mut captures) = glob_captures(rest, &text[end..]) {
+            captures.insert(0, text[..end].to_string());
+            return Some(captures);
+        }
+    }
+    None
+}
+
+/// Replaces `{1}`, `{2}`, etc. in `template` with the matching glob capture
+pub fn fill_captures(
+    template: &str,
+    captures: &[String],
+    format: impl Fn(&str) -> String,
+) -> String {
+    captures
+        .iter()
+        .enumerate()
+        .fold(template.to_string(), |filled, (i, capture)| {
+            filled.replace(&format!("{{{}}}", i + 1), &format(capture))
+        })
+}
+
+/// Whether `template` references any glob captures with `{1}`, `{2}`, etc.
+pub fn has_captures(template: &str) -> bool {
+    template.split('{').skip(1).any(|rest| {
+        rest.split_once('}').is_some_and(|(index, _)| {
+            !index.is_empty() && index.chars().all(|c| c.is_ascii_digit())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_literal_patterns_exactly() {
+        assert_eq!(glob_captures("users", "users"), Some(vec![]));
+        assert_eq!(glob_captures("users", "users_aggregate"), None);
+        assert_eq!(glob_captures("", ""), Some(vec![]));
+    }
+
+    #[test]
+    fn captures_each_wildcard() {
+        assert_eq!(
+            glob_captures("*_id", "user_id"),
+            Some(vec!["user".to_string()])
+        );
+        assert_eq!(
+            glob_captures("dr_*_*", "dr_form_field"),
+            Some(vec!["form".to_string(), "field".to_string()])
+        );
+        assert_eq!(glob_captures("*", ""), Some(vec![String::new()]));
+        assert_eq!(glob_captures("*_id", "user_ids"), None);
+    }
+
+    #[test]
+    fn earlier_wildcards_match_as_little_as_possible() {
+        assert_eq!(
+            glob_captures("*_*", "parent_user_id"),
+            Some(vec!["parent".to_string(), "user_id".to_string()])
+        );
+    }
+
+    #[test]
+    fn captures_multibyte_characters() {
+        assert_eq!(glob_captures("*é", "caféé"), Some(vec!["café".to_string()]));
+    }
+
+    #[test]
+    fn fills_captures_in_order() {
+        let captures = vec!["user".to_string(), "post".to_string()];
+        assert_eq!(
+            fill_captures("{1}s_{2}s", &captures, str::to_string),
+            "users_posts"
+        );
+        assert_eq!(
+            fill_captures("{1}Id", &captures, str::to_uppercase),
+            "USERId"
+        );
+    }
+
+    #[test]
+    fn finds_capture_references() {
+        assert!(has_captures("Data.Id.{1}Id"));
+        assert!(has_captures("{12}"));
+        assert!(!has_captures("Data.Id.UserId"));
+        assert!(!has_captures("Data.{name}"));
+        assert!(!has_captures("{}"));
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{read_dir, remove_dir_all},
+};
+
+use crate::{
+    hasura_types::{scalar_has_instance, scalar_type},
+    write::write,
+};
+
+use super::{
+    parse_outside_types::{Mod, OutsideTypes, COMPARISON_EXP},
+    workspace::{MockInstance, WorkspaceConfig},
+};
+
+/// Written to every mock package, so only packages this tool generated are removed
+const MOCK_MARKER: &str = ".pursgql-mock";
+
+/// Removes the mock packages written by a previous run, leaving anything else in the mock dir
+pub fn remove_mock_outside_types(workspace_config: &WorkspaceConfig) {
+    let Ok(entries) = read_dir(&workspace_config.mock_outside_types.dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.join(MOCK_MARKER).is_file() {
+            remove_dir_all(&path)
+                .unwrap_or_else(|err| panic!("Failed to remove mock package {path:?}: {err}"));
+        }
+    }
+}
+
+/// Writes a newtype module for every outside type in a mocked package that resolved a field,
+/// wrapping the purescript type of the fields' scalar, along with a spago.yaml for each package.
+/// Generated enums are skipped as they are written by the schema generation.
+pub fn write_mock_outside_types(outside_types: &OutsideTypes, workspace_config: &WorkspaceConfig) {
+    let config = &workspace_config.mock_outside_types;
+    let enum_libs = workspace_config.enum_libs();
+
+    // Package name -> dependencies
+    let mut packages: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (module, scalars) in outside_types.resolved_scalars() {
+        if enum_libs.contains(&module.package.as_str()) || !config.mocks(&module.package) {
+            continue;
+        }
+
+        let types: BTreeSet<(String, Option<(&str, &str)>)> =
+            scalars.iter().map(|scalar| scalar_type(scalar)).collect();
+        let (type_, import) = types
+            .first()
+            .expect("Resolved outside types should have at least one scalar.");
+        if types.len() > 1 {
+            let all: Vec<&str> = types.iter().map(|(t, _)| t.as_str()).collect();
+            println!(
+                "Mocking {} as {type_}, but it is used for fields of types {}",
+                module.name,
+                all.join(", ")
+            );
+        }
+
+        // Only derive the instances the mocked type has, so the mock compiles
+        let instances: Vec<&MockInstance> = config
+            .instances
+            .iter()
+            .filter(|instance| scalar_has_instance(type_, &instance.class))
+            .collect();
+
+        let dependencies = packages.entry(&module.package).or_default();
+        dependencies.insert("prelude");
+        dependencies.extend(instances.iter().map(|i| i.package.as_str()));
+        if let Some((_, package)) = import {
+            dependencies.insert(package);
+        }
+
+        write(
+            &mock_path(&config.dir, module),
+            &mock_module(module, type_, *import, &instances),
+        );
+    }
+
+    // Comparison expression wrappers are mocked as graphql-client's comparison expression
+    for module in outside_types.resolved_comparison_exps() {
+        if module.package == COMPARISON_EXP.2
+            || enum_libs.contains(&module.package.as_str())
+            || !config.mocks(&module.package)
+        {
+            continue;
+        }
+        packages
+            .entry(&module.package)
+            .or_default()
+            .insert(COMPARISON_EXP.2);
+        write(
+            &mock_path(&config.dir, module),
+            &mock_comparison_exp_module(module),
+        );
+    }
+
+    for (package, dependencies) in packages {
+        write(
+            &format!("{}/{package}/spago.yaml", config.dir),
+            &mock_spago_yaml(package, &dependencies),
+        );
+        write(&format!("{}/{package}/{MOCK_MARKER}", config.dir), "");
+    }
+}
+
+fn mock_path(dir: &str, module: &Mod) -> String {
+    format!(
+        "{dir}/{}/src/{}.purs",
+        module.package,
+        module.import.replace('.', "/")
+    )
+}
+
+fn mock_comparison_exp_module(module: &Mod) -> String {
+    let (name, import, _) = COMPARISON_EXP;
+    format!(
+        "module {} ({}) where\n\nimport {import} ({name})\n\ntype {} a = {name} a\n",
+        module.import, module.name, module.name
+    )
+}
+
+fn mock_module(
+    module: &Mod,
+    type_: &str,
+    import: Option<(&str, &str)>,
+    instances: &[&MockInstance],
+) -> String {
+    let mut classes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for instance in instances.iter() {
+        classes
+            .entry(&instance.module)
+            .or_default()
+            .push(format!("class {}", instance.class));
+    }
+
+    let mut imports = vec![];
+    if classes.remove("Prelude").is_some() {
+        imports.push("import Prelude\n".to_string());
+    }
+    if let Some((import, _)) = import {
+        classes.entry(import).or_default().push(type_.to_string());
+    }
+    imports.extend(
+        classes
+            .iter()
+            .map(|(import, names)| format!("import {import} ({})\n", names.join(", "))),
+    );
+
+    let instances: String = instances
+        .iter()
+        .map(|i| format!("\nderive newtype instance {} {}", i.class, module.name))
+        .collect();
+
+    let imports = match imports.is_empty() {
+        true => String::new(),
+        false => imports.join("") + "\n",
+    };
+
+    format!(
+        "module {} ({}) where\n\n{imports}newtype {} = {} {type_}\n{instances}\n",
+        module.import, module.name, module.name, module.name
+    )
+}
+
+fn mock_spago_yaml(package: &str, dependencies: &BTreeSet<&str>) -> String {
+    let dependencies: String = dependencies
+        .iter()
+        .map(|dependency| format!("    - {dependency}\n"))
+        .collect();
+    format!(
+        r#"package:
+  name: {package}
+  dependencies:
+{dependencies}"#
+    )
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::File,
     io::Read,
 };
@@ -7,63 +7,426 @@ use std::{
 use stringcase::pascal_case;
 use yaml_rust2::{yaml, Yaml};
 
-use crate::write::write;
+use super::{
+    glob::{fill_captures, glob_captures, has_captures},
+    yaml_positions::{Location, YamlPositions},
+};
+
+#[derive(Debug, Default)]
+pub struct OutsideTypes {
+    /// Outside types keyed by object (table) name then field name
+    pub objects: HashMap<String, Table>,
+    /// Explicit outside types keyed by root field name then argument name
+    pub arguments: HashMap<String, Table>,
+    /// Pattern based rules, used when there is no explicit entry for a field
+    pub rules: Vec<Rule>,
+    /// The types wrapping outside types in comparison expressions, keyed by scalar name
+    comparison_exps: Object,
+    /// Every table, field, template key and rule in the config
+    defined: BTreeSet<Location>,
+    /// The definitions that have resolved the type of at least one field
+    used: HashSet<Location>,
+    /// The GraphQL scalars of the fields each type has resolved, used to mock the type
+    scalars: HashMap<Mod, BTreeSet<String>>,
+    /// The comparison expression wrappers that have wrapped a field type, used to mock them
+    comparison_wrappers: HashSet<Mod>,
+}
+
+/// graphql-client's comparison expression type, as `(name, import, package)`,
+/// which wraps outside types unless `comparison_exps` says otherwise
+pub const COMPARISON_EXP: (&str, &str, &str) = (
+    "ComparisonExp",
+    "GraphQL.Hasura.ComparisonExp",
+    "graphql-client",
+);
+
+impl OutsideTypes {
+    pub fn extend(&mut self, other: OutsideTypes) {
+        self.objects.extend(other.objects);
+        self.arguments.extend(other.arguments);
+        self.rules.extend(other.rules);
+        self.comparison_exps.extend(other.comparison_exps);
+        self.defined.extend(other.defined);
+        self.used.extend(other.used);
+        for (module, scalars) in other.scalars {
+            self.scalars.entry(module).or_default().extend(scalars);
+        }
+        self.comparison_wrappers.extend(other.comparison_wrappers);
+    }
+
+    /// Finds the outside type for a field, checking the explicit entries for every
+    /// candidate table before falling back to the rules, in the order they were defined.
+    /// Rules only apply to candidates that are tables in the current schema, as held by `tables`.
+    /// `scalar` is the GraphQL scalar of the field, or of its elements for lists.
+    pub fn find(
+        &mut self,
+        candidates: &[&str],
+        field: &str,
+        scalar: &str,
+        tables: &HashSet<String>,
+    ) -> Option<Mod> {
+        let (module, used) = candidates
+            .iter()
+            .find_map(|table| self.objects.get(*table)?.find(field))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .filter(|table| tables.contains(**table))
+                    .find_map(|table| {
+                        self.rules
+                            .iter()
+                            .find_map(|rule| rule.apply(table, field, tables))
+                    })
+            })?;
+        self.resolved(&module, used, scalar);
+        Some(module)
+    }
 
-use super::workspace::WorkspaceConfig;
+    /// Finds an explicit outside type for an argument of a field
+    pub fn find_argument(&mut self, field: &str, argument: &str, scalar: &str) -> Option<Mod> {
+        let (module, used) = self.arguments.get(field)?.find(argument)?;
+        self.resolved(&module, used, scalar);
+        Some(module)
+    }
 
-pub type OutsideTypes = HashMap<String, Object>;
-type Object = HashMap<String, Mod>;
+    fn resolved(&mut self, module: &Mod, used: Vec<Location>, scalar: &str) {
+        self.used.extend(used);
+        self.scalars
+            .entry(module.clone())
+            .or_default()
+            .insert(scalar.to_string());
+    }
 
-pub fn fetch_all_outside_types(workspace_config: &WorkspaceConfig) -> OutsideTypes {
+    /// Finds the type to wrap outside types in for comparison expressions on a scalar,
+    /// e.g. `String` for `String_comparison_exp`.
+    /// Falls back to the `default` entry and then to graphql-client's `ComparisonExp`.
+    pub fn find_comparison_exp(&mut self, scalar: &str) -> Mod {
+        match self
+            .comparison_exps
+            .get(scalar)
+            .or_else(|| self.comparison_exps.get("default"))
+        {
+            Some(OutsideType { module, defined_at }) => {
+                self.used.insert(defined_at.clone());
+                self.comparison_wrappers.insert(module.clone());
+                module.clone()
+            }
+            None => {
+                let (name, import, package) = COMPARISON_EXP;
+                Mod {
+                    import: import.to_string(),
+                    name: name.to_string(),
+                    package: package.to_string(),
+                }
+            }
+        }
+    }
+
+    /// Lists every type referenced by the config as `(package, import, name)`,
+    /// along with the first place it is defined.
+    /// Rule types that depend on the field name are skipped.
+    pub fn modules(&self) -> BTreeMap<(String, String, String), &Location> {
+        let mut modules: Vec<(&Mod, &Location)> = vec![];
+        for table in self.objects.values().chain(self.arguments.values()) {
+            modules.extend(table.types.values().map(|t| (&t.module, &t.defined_at)));
+        }
+        modules.extend(
+            self.comparison_exps
+                .values()
+                .map(|t| (&t.module, &t.defined_at)),
+        );
+        for rule in self.rules.iter() {
+            match &rule.kind {
+                RuleKind::Template(template) => {
+                    modules.extend(template.values().map(|t| (&t.module, &t.defined_at)))
+                }
+                RuleKind::Field { type_, .. }
+                    if !has_captures(&type_.import) && !has_captures(&type_.name) =>
+                {
+                    modules.push((type_, &rule.defined_at))
+                }
+                RuleKind::Field { .. } => {}
+            }
+        }
+
+        let mut first_defined = BTreeMap::new();
+        for (module, defined_at) in modules {
+            let key = (
+                module.package.clone(),
+                module.import.clone(),
+                module.name.clone(),
+            );
+            first_defined
+                .entry(key)
+                .and_modify(|first: &mut &Location| *first = (*first).min(defined_at))
+                .or_insert(defined_at);
+        }
+        first_defined
+    }
+
+    /// Lists every type that has resolved a field, along with the GraphQL scalars of those fields
+    pub fn resolved_scalars(&self) -> &HashMap<Mod, BTreeSet<String>> {
+        &self.scalars
+    }
+
+    /// Lists every comparison expression wrapper from `comparison_exps` that has wrapped a field type
+    pub fn resolved_comparison_exps(&self) -> &HashSet<Mod> {
+        &self.comparison_wrappers
+    }
+
+    /// Moves the field types and comparison expression wrappers in the packages `is_moved`
+    /// accepts into `package`. Used to point the schemas at a single package of mocks.
+    pub fn set_package(&mut self, package: &str, is_moved: impl Fn(&str) -> bool) {
+        let mut modules: Vec<&mut Mod> = vec![];
+        for table in self.objects.values_mut().chain(self.arguments.values_mut()) {
+            modules.extend(table.types.values_mut().map(|t| &mut t.module));
+        }
+        for rule in self.rules.iter_mut() {
+            match &mut rule.kind {
+                RuleKind::Template(template) => {
+                    modules.extend(template.values_mut().map(|t| &mut t.module))
+                }
+                RuleKind::Field { type_, .. } => modules.push(type_),
+            }
+        }
+        modules.extend(self.comparison_exps.values_mut().map(|t| &mut t.module));
+        for module in modules {
+            if is_moved(&module.package) {
+                module.package = package.to_string();
+            }
+        }
+    }
+
+    /// Lists the definitions in the config that haven't resolved any field types
+    pub fn unused(&self) -> Vec<&Location> {
+        self.defined
+            .iter()
+            .filter(|location| !self.used.contains(*location))
+            .collect()
+    }
+}
+
+/// An outside type along with where it was defined in the config
+#[derive(Debug, Clone)]
+pub struct OutsideType {
+    pub module: Mod,
+    pub defined_at: Location,
+}
+
+/// The outside types for the fields of a table or the arguments of a field
+#[derive(Debug)]
+pub struct Table {
+    pub types: Object,
+    pub defined_at: Location,
+}
+
+impl Table {
+    /// Finds the type of a field, along with the definitions used to find it
+    fn find(&self, field: &str) -> Option<(Mod, Vec<Location>)> {
+        let outside_type = self.types.get(field)?;
+        Some((
+            outside_type.module.clone(),
+            vec![self.defined_at.clone(), outside_type.defined_at.clone()],
+        ))
+    }
+}
+
+/// A rule applying outside types to every table and field matching its globs
+#[derive(Debug)]
+pub struct Rule {
+    tables: String,
+    kind: RuleKind,
+    defined_at: Location,
+}
+
+#[derive(Debug)]
+enum RuleKind {
+    /// Applies a template to every matching table
+    Template(Object),
+    /// Applies a type to every matching field.
+    /// The type and `table_exists` can reference the field glob captures as `{1}`, `{2}`, etc.
+    Field {
+        fields: String,
+        type_: Mod,
+        table_exists: Option<String>,
+    },
+}
+
+impl Rule {
+    /// Applies the rule to a field, returning the type along with the definitions used to find it
+    fn apply(
+        &self,
+        table: &str,
+        field: &str,
+        tables: &HashSet<String>,
+    ) -> Option<(Mod, Vec<Location>)> {
+        glob_captures(&self.tables, table)?;
+        match &self.kind {
+            RuleKind::Template(template) => {
+                let outside_type = template.get(field)?;
+                Some((
+                    outside_type.module.clone(),
+                    vec![self.defined_at.clone(), outside_type.defined_at.clone()],
+                ))
+            }
+            RuleKind::Field {
+                fields,
+                type_,
+                table_exists,
+            } => {
+                let captures = glob_captures(fields, field)?;
+                if let Some(table_exists) = table_exists {
+                    if !tables.contains(&fill_captures(table_exists, &captures, str::to_string)) {
+                        return None;
+                    }
+                }
+                let module = Mod {
+                    import: fill_captures(&type_.import, &captures, pascal_case),
+                    name: fill_captures(&type_.name, &captures, pascal_case),
+                    package: type_.package.clone(),
+                };
+                Some((module, vec![self.defined_at.clone()]))
+            }
+        }
+    }
+}
+
+pub type Object = HashMap<String, OutsideType>;
+
+pub fn fetch_all_outside_types() -> OutsideTypes {
     let outside_types_env =
         std::env::var("OUTSIDE_TYPES_YAML").expect("OUTSIDE_TYPES_YAML must be set");
 
     let outside_type_locs: Vec<&str> = outside_types_env.split(",").collect();
 
-    let mut outside_types: OutsideTypes = HashMap::new();
+    let mut outside_types = OutsideTypes::default();
     for loc in outside_type_locs.iter() {
-        let types = fetch_outside_types(loc, workspace_config);
+        let types = fetch_outside_types(loc);
         outside_types.extend(types);
     }
     outside_types
 }
 
-pub fn fetch_outside_types(location: &str, workspace_config: &WorkspaceConfig) -> OutsideTypes {
+pub fn fetch_outside_types(location: &str) -> OutsideTypes {
     let mut f =
         File::open(location).expect(&format!("Outside types yaml file not found at {location}"));
     let mut s = String::new();
     f.read_to_string(&mut s)
         .expect("Failed to read outside types file to string.");
 
-    let docs =
-        yaml::YamlLoader::load_from_str(&s).expect("Failed to parse outside types file as yaml.");
+    let docs = yaml::YamlLoader::load_from_str(&s)
+        .unwrap_or_else(|e| panic!("Failed to parse outside types file {location} as yaml: {e}"));
+    let positions = YamlPositions::load(location, &s);
     if let Yaml::Hash(hash) = &docs[0] {
-        let types = to_types(hash.get(&Yaml::String("types".to_string())));
-        let templates: HashMap<String, Object> =
-            to_templates(hash.get(&Yaml::String("templates".to_string())), &types);
+        let types = to_types(hash.get(&Yaml::String("types".to_string())), &positions);
+        let templates: HashMap<String, Object> = to_templates(
+            hash.get(&Yaml::String("templates".to_string())),
+            &types,
+            &positions,
+        );
 
-        let outside_types = to_outside_types(
+        let objects = to_outside_types(
             hash.get(&Yaml::String("outside_types".to_string()))
                 .expect("Your outside types yaml should have a top level key 'outside_types'"),
+            "outside_types",
+            &types,
+            &templates,
+            &positions,
+        );
+
+        // Arguments are optional and use the same shape as outside types,
+        // but are keyed by the field the arguments belong to
+        let arguments = match hash.get(&Yaml::String("arguments".to_string())) {
+            Some(yaml @ Yaml::Hash(_)) => {
+                to_outside_types(yaml, "arguments", &types, &templates, &positions)
+            }
+            Some(_) => panic!(
+                "Your outside types .yaml should have an arguments key with a hash of fields to argument types"
+            ),
+            None => HashMap::new(),
+        };
+
+        let rules = to_rules(
+            hash.get(&Yaml::String("rules".to_string())),
             &types,
             &templates,
+            &positions,
         );
 
-        write_types(&outside_types, workspace_config);
+        let comparison_exps = match hash.get(&Yaml::String("comparison_exps".to_string())) {
+            Some(Yaml::Hash(scalars)) => scalars
+                .iter()
+                .filter_map(|(scalar, type_value)| {
+                    let scalar = scalar.as_str()?;
+                    let path = ["comparison_exps", scalar];
+                    let comparison_exp = OutsideType {
+                        module: to_type_value(type_value, &types, &positions, &path),
+                        defined_at: positions.at(&path),
+                    };
+                    Some((scalar.to_string(), comparison_exp))
+                })
+                .collect(),
+            Some(_) => panic!(
+                "Your outside types .yaml should have a comparison_exps key with a hash of scalars to types"
+            ),
+            None => HashMap::new(),
+        };
 
-        return outside_types;
+        // Keep track of everything defined so unused config can be reported after generation
+        let mut defined = BTreeSet::new();
+        for table in objects.values().chain(arguments.values()) {
+            defined.insert(table.defined_at.clone());
+            defined.extend(table.types.values().map(|t| t.defined_at.clone()));
+        }
+        for template in templates.values() {
+            defined.extend(template.values().map(|t| t.defined_at.clone()));
+        }
+        defined.extend(rules.iter().map(|rule| rule.defined_at.clone()));
+        defined.extend(comparison_exps.values().map(|t| t.defined_at.clone()));
+
+        OutsideTypes {
+            objects,
+            arguments,
+            rules,
+            comparison_exps,
+            defined,
+            used: HashSet::new(),
+            scalars: HashMap::new(),
+            comparison_wrappers: HashSet::new(),
+        }
     } else {
         panic!("Your outside types yaml should be a hash of at least one key: 'outside_types'");
     }
 }
 
-fn to_types(yaml: Option<&Yaml>) -> impl Fn(&str, &str) -> Option<Mod> {
-    let types: HashMap<String, String> = match yaml {
+fn to_types(yaml: Option<&Yaml>, positions: &YamlPositions) -> impl Fn(&str, &str) -> Option<Mod> {
+    // The module and package for each type, where `$` is replaced by the type name
+    let types: HashMap<String, (String, String)> = match yaml {
         Some(Yaml::Hash(types_hash)) => {
             let mut hash = HashMap::new();
-            for key_value in types_hash.iter() {
-                if let (Yaml::String(key), Yaml::String(value)) = key_value {
-                    hash.insert(key.clone(), value.clone());
+            for (key, value) in types_hash.iter() {
+                if let Yaml::String(key) = key {
+                    let path = ["types", key];
+                    let module_package = match value {
+                        Yaml::String(value) => match split_inline(value).as_slice() {
+                            [_, module, package] => (module.to_string(), package.to_string()),
+                            _ => invalid_type(
+                                positions,
+                                &path,
+                                "Types should be of the format '$, Module.$, package'",
+                            ),
+                        },
+                        Yaml::Hash(_) => (
+                            get_str(value, "module", positions, &path).unwrap_or_else(|| {
+                                invalid_type(positions, &path, "Types need a 'module' key")
+                            }),
+                            get_str(value, "package", positions, &path).unwrap_or_else(|| {
+                                invalid_type(positions, &path, "Types need a 'package' key")
+                            }),
+                        ),
+                        _ => invalid_type(positions, &path, "Types should be a string or a hash"),
+                    };
+                    hash.insert(key.clone(), module_package);
                 }
             }
             hash
@@ -77,58 +440,53 @@ fn to_types(yaml: Option<&Yaml>) -> impl Fn(&str, &str) -> Option<Mod> {
     };
 
     move |name: &str, type_name: &str| -> Option<Mod> {
-        match types.get(name) {
-            Some(import) => Some(Mod {
-                import: import
-                    .split(", ")
-                    .collect::<Vec<&str>>()
-                    .get(1)
-                    .expect(&format!("No import found for type {name}"))
-                    .replace("$", type_name),
-                name: type_name.to_string(),
-                package: import
-                    .split(", ")
-                    .collect::<Vec<&str>>()
-                    .get(2)
-                    .expect(&format!("No package found for type {name}"))
-                    .to_string(),
-            }),
-            None => None,
-        }
+        types.get(name).map(|(module, package)| Mod {
+            import: module.replace("$", type_name),
+            name: type_name.to_string(),
+            package: package.clone(),
+        })
     }
 }
 
 fn to_outside_types(
     yaml: &Yaml,
+    key: &str,
     types_fn: &impl Fn(&str, &str) -> Option<Mod>,
     templates: &HashMap<String, Object>,
-) -> OutsideTypes {
-    let mut outside_types: OutsideTypes = HashMap::new();
+    positions: &YamlPositions,
+) -> HashMap<String, Table> {
+    let mut outside_types: HashMap<String, Table> = HashMap::new();
 
     if let Yaml::Hash(outside_types_hash) = yaml {
         for module_entries in outside_types_hash.iter() {
             if let (Yaml::String(module_name), Yaml::Hash(module_hash)) = module_entries {
-                let mut table: Object = HashMap::new();
                 // Add the template types if the table has a 'with' key
-                if let Some(Yaml::String(template_str)) =
-                    module_hash.get(&Yaml::String("with".to_string()))
-                {
-                    if let Some(template) = templates.get(template_str) {
-                        table.extend(template.iter().map(|(k, v)| (k.clone(), v.clone())));
-                    }
-                }
+                let mut table: Object = match module_hash.get(&Yaml::String("with".to_string())) {
+                    Some(with) => with_templates(with, templates),
+                    None => HashMap::new(),
+                };
 
                 // Add the types from the module
                 for field_type in module_hash.iter() {
-                    if let (Yaml::String(field_name), Yaml::String(type_name)) = field_type {
+                    if let (Yaml::String(field_name), type_value) = field_type {
                         if field_name == "with" {
                             continue;
                         }
-                        let value = to_type_value(type_name, types_fn);
+                        let path = [key, module_name, field_name];
+                        let value = OutsideType {
+                            module: to_type_value(type_value, types_fn, positions, &path),
+                            defined_at: positions.at(&path),
+                        };
                         table.insert(field_name.clone(), value);
                     }
                 }
-                outside_types.insert(module_name.clone(), table);
+                outside_types.insert(
+                    module_name.clone(),
+                    Table {
+                        types: table,
+                        defined_at: positions.at(&[key, module_name]),
+                    },
+                );
             }
         }
     }
@@ -136,26 +494,112 @@ fn to_outside_types(
     outside_types
 }
 
+fn to_rules(
+    yaml: Option<&Yaml>,
+    types_fn: &impl Fn(&str, &str) -> Option<Mod>,
+    templates: &HashMap<String, Object>,
+    positions: &YamlPositions,
+) -> Vec<Rule> {
+    let rules = match yaml {
+        Some(Yaml::Array(rules)) => rules,
+        Some(_) => {
+            panic!("Your outside types .yaml should have a rules key with an array of rules")
+        }
+        None => return vec![],
+    };
+
+    rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            let index = index.to_string();
+            let path = ["rules", index.as_str()];
+            let get = |key: &str| get_str(rule, key, positions, &path);
+            let tables = get("tables").unwrap_or("*".to_string());
+            let with = match &rule["with"] {
+                Yaml::BadValue => None,
+                with => Some(with_templates(with, templates)),
+            };
+            let kind = match (with, get("fields"), &rule["type"]) {
+                (Some(template), None, Yaml::BadValue) => RuleKind::Template(template),
+                (None, Some(fields), type_) if !type_.is_badvalue() => RuleKind::Field {
+                    fields,
+                    type_: to_type_value(type_, types_fn, positions, &["rules", &index, "type"]),
+                    table_exists: get("table_exists"),
+                },
+                _ => invalid_type(
+                    positions,
+                    &path,
+                    "Rules should have either a 'with' key or both 'fields' and 'type' keys",
+                ),
+            };
+            Rule {
+                tables,
+                kind,
+                defined_at: positions.at(&path),
+            }
+        })
+        .collect()
+}
+
+/// Merges the templates named by a `with` key, which can be a single template name or a list.
+/// Templates later in the list take precedence over earlier ones.
+fn with_templates(with: &Yaml, templates: &HashMap<String, Object>) -> Object {
+    let mut merged = HashMap::new();
+    for name in to_template_names(with) {
+        let template = templates
+            .get(&name)
+            .unwrap_or_else(|| panic!("Template not found: {name}"));
+        merged.extend(template.clone());
+    }
+    merged
+}
+
+fn to_template_names(yaml: &Yaml) -> Vec<String> {
+    match yaml {
+        Yaml::String(name) => vec![name.clone()],
+        Yaml::Array(names) => names
+            .iter()
+            .map(|name| match name {
+                Yaml::String(name) => name.clone(),
+                _ => panic!("Template lists should only contain template names"),
+            })
+            .collect(),
+        _ => panic!("Templates should be referenced by a name or a list of names"),
+    }
+}
+
 fn to_templates(
     yaml: Option<&Yaml>,
     types_fn: &impl Fn(&str, &str) -> Option<Mod>,
+    positions: &YamlPositions,
 ) -> HashMap<String, Object> {
-    let mut templates: HashMap<String, Object> = HashMap::new();
+    // Parse each template along with the names of the templates it extends
+    let mut unresolved: HashMap<String, (Vec<String>, Object)> = HashMap::new();
 
     match yaml {
         Some(Yaml::Hash(templates_hash)) => {
             for key_value in templates_hash.iter() {
                 if let (Yaml::String(key), Yaml::Hash(template_types)) = key_value {
+                    let mut extends = vec![];
                     let mut values = HashMap::new();
                     for name_value in template_types.iter() {
-                        if let (Yaml::String(type_name), Yaml::String(type_value)) = name_value {
-                            let value = to_type_value(type_value, types_fn);
-                            values.insert(type_name.clone(), value);
-                        } else {
-                            panic!("Mismated yaml type name");
+                        match name_value {
+                            (Yaml::String(type_name), parents) if type_name == "extends" => {
+                                extends = to_template_names(parents);
+                            }
+                            (Yaml::String(type_name), type_value) => {
+                                let path = ["templates", key, type_name];
+                                let value = OutsideType {
+                                    module: to_type_value(type_value, types_fn, positions, &path),
+                                    defined_at: positions.at(&path),
+                                };
+                                values.insert(type_name.clone(), value);
+                            }
+                            _ => panic!("Mismated yaml type name"),
                         }
                     }
-                    templates.insert(key.clone(), values);
+                    unresolved.insert(key.clone(), (extends, values));
                 }
             }
         }
@@ -164,43 +608,129 @@ fn to_templates(
         }
         None => {}
     }
+
+    let mut templates: HashMap<String, Object> = HashMap::new();
+    for name in unresolved.keys() {
+        resolve_template(name, &unresolved, &mut templates, &mut vec![]);
+    }
     templates
 }
 
-fn to_type_value(type_value: &String, types_fn: &impl Fn(&str, &str) -> Option<Mod>) -> Mod {
-    if type_value.contains('=') {
-        types_fn(
-            type_value
-                .split('=')
-                .next()
-                .expect(&format!("No type name found before '=' for: {type_value}.")),
-            type_value
-                .split('=')
-                .last()
-                .expect(&format!("No type value found after '=' for: {type_value}.")),
-        )
-        .expect(&format!("Type not found: {}", type_value))
-    } else if type_value.contains(", ") {
-        let mut values = type_value.split(", ");
-        let name = values
-            .next()
-            .expect(&format!("No type name found for inline type {type_value}."));
-        let import = values.next().expect(&format!(
-            "No import found (second comma separated value) for inline type {type_value}."
-        ));
-        let package: &str = values.next().expect(&format!(
-            "No package found (third comma separated value) for inline type {type_value}."
-        ));
-        Mod {
-            import: import.to_string(),
-            name: name.to_string(),
-            package: package.to_string(),
+/// Flattens a template and the templates it extends into a single template.
+/// A template's own types take precedence over the types it extends,
+/// and later templates in `extends` take precedence over earlier ones.
+fn resolve_template(
+    name: &str,
+    unresolved: &HashMap<String, (Vec<String>, Object)>,
+    templates: &mut HashMap<String, Object>,
+    extending: &mut Vec<String>,
+) -> Object {
+    if let Some(template) = templates.get(name) {
+        return template.clone();
+    }
+    if extending.iter().any(|n| n == name) {
+        panic!(
+            "Templates cannot extend themselves: {} -> {name}",
+            extending.join(" -> ")
+        );
+    }
+    let (extends, values) = unresolved
+        .get(name)
+        .unwrap_or_else(|| panic!("Template not found: {name}"));
+
+    extending.push(name.to_string());
+    let mut template: Object = HashMap::new();
+    for parent in extends {
+        template.extend(resolve_template(parent, unresolved, templates, extending));
+    }
+    extending.pop();
+
+    template.extend(values.clone());
+    templates.insert(name.to_string(), template.clone());
+    template
+}
+
+/// Parses a type mapping, which is one of:
+/// - a `types` shorthand: `id=UserId`
+/// - an inline type: `UserId, Data.Id.UserId, oa-ids`
+/// - a structured type: `{ type: UserId, module: Data.Id.UserId, package: oa-ids }`
+/// - a structured `types` shorthand: `{ type: UserId, from: id }`
+fn to_type_value(
+    type_value: &Yaml,
+    types_fn: &impl Fn(&str, &str) -> Option<Mod>,
+    positions: &YamlPositions,
+    path: &[&str],
+) -> Mod {
+    let from_types = |types_name: &str, type_name: &str| {
+        types_fn(types_name, type_name).unwrap_or_else(|| {
+            invalid_type(
+                positions,
+                path,
+                &format!("'{types_name}' is not defined in the 'types' section"),
+            )
+        })
+    };
+
+    match type_value {
+        Yaml::String(type_value) => match type_value.split_once('=') {
+            Some((types_name, type_name)) => from_types(types_name.trim(), type_name.trim()),
+            None => match split_inline(type_value).as_slice() {
+                [name, import, package] => Mod {
+                    import: import.to_string(),
+                    name: name.to_string(),
+                    package: package.to_string(),
+                },
+                _ => invalid_type(
+                    positions,
+                    path,
+                    &format!(
+                        "'{type_value}' should either reference the 'types' section, like 'id=UserId', or be of the format 'Type, Module, package'"
+                    ),
+                ),
+            },
+        },
+        Yaml::Hash(_) => {
+            let get = |key: &str| get_str(type_value, key, positions, path);
+            match (get("type"), get("from"), get("module"), get("package")) {
+                (Some(name), Some(from), None, None) => from_types(&from, &name),
+                (Some(name), None, Some(import), Some(package)) => Mod {
+                    import,
+                    name,
+                    package,
+                },
+                _ => invalid_type(
+                    positions,
+                    path,
+                    "Structured types should have a 'type' key and either a 'from' key or both 'module' and 'package' keys",
+                ),
+            }
         }
-    } else {
-        panic!("Only the 'with' key can contain string template types");
+        _ => invalid_type(positions, path, "Types should be a string or a hash"),
     }
 }
 
+/// Splits a comma separated inline type, ignoring whitespace and empty values
+fn split_inline(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Gets an optional string value from a yaml hash
+fn get_str(yaml: &Yaml, key: &str, positions: &YamlPositions, path: &[&str]) -> Option<String> {
+    match &yaml[key] {
+        Yaml::String(value) => Some(value.trim().to_string()),
+        Yaml::BadValue => None,
+        _ => invalid_type(positions, path, &format!("'{key}' should be a string")),
+    }
+}
+
+fn invalid_type(positions: &YamlPositions, path: &[&str], reason: &str) -> ! {
+    panic!("Invalid outside type at {}: {reason}", positions.at(path))
+}
+
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct Mod {
     pub import: String,
@@ -208,48 +738,210 @@ pub struct Mod {
     pub package: String,
 }
 
-fn write_types(outside_types: &OutsideTypes, workspace_config: &WorkspaceConfig) {
-    let mut to_write = HashSet::new();
-    for (_, table) in outside_types.iter() {
-        for (_, module) in table.iter() {
-            to_write.insert(module.clone());
-        }
-    }
-    let mock_outside_types = std::env::var("MOCK_OUTSIDE_TYPES");
-    if mock_outside_types.is_ok() {
-        let lib_path = workspace_config.shared_graphql_enums_dir.clone();
-        let postgres_enums_lib = pascal_case(&workspace_config.postgres_enums_lib);
-        let gql_enums_lib = pascal_case(&workspace_config.shared_graphql_enums_lib);
-        for module in to_write.iter() {
-            let is_enum_mod = module.import.contains(&postgres_enums_lib)
-                || module.import.contains(&gql_enums_lib);
-            if is_enum_mod {
-                // Don't bother generating mock for generated enum types
-                continue;
-            }
-            write(
-                &format!("{lib_path}/oa-ids/src/{}.purs", &module.import),
-                &mocked_id_module(&module),
-            );
-            write(&format!("{lib_path}/oa-ids/spago.yaml"), &ids_spago_yaml());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str) -> Mod {
+        Mod {
+            import: format!("Data.{name}"),
+            name: name.to_string(),
+            package: "ids".to_string(),
         }
     }
-}
 
-fn ids_spago_yaml() -> String {
-    r#"package:
-  name: oa-ids
-  dependencies:
-    - prelude
-"#
-    .to_string()
-}
+    fn location(path: &[&str]) -> Location {
+        YamlPositions::load("outside_types.yaml", "").at(path)
+    }
+
+    fn outside_type(name: &str) -> OutsideType {
+        OutsideType {
+            module: module(name),
+            defined_at: location(&[name]),
+        }
+    }
+
+    fn field_rule(fields: &str, type_name: &str, table_exists: Option<&str>) -> Rule {
+        Rule {
+            tables: "*".to_string(),
+            kind: RuleKind::Field {
+                fields: fields.to_string(),
+                type_: module(type_name),
+                table_exists: table_exists.map(str::to_string),
+            },
+            defined_at: location(&[fields]),
+        }
+    }
+
+    fn outside_types(objects: &[(&str, &str, &str)], rules: Vec<Rule>) -> OutsideTypes {
+        let mut outside_types = OutsideTypes {
+            rules,
+            ..Default::default()
+        };
+        for (table, field, type_name) in objects {
+            outside_types
+                .objects
+                .entry(table.to_string())
+                .or_insert_with(|| Table {
+                    types: Object::new(),
+                    defined_at: location(&[table]),
+                })
+                .types
+                .insert(field.to_string(), outside_type(type_name));
+        }
+        outside_types
+    }
+
+    fn tables(tables: &[&str]) -> HashSet<String> {
+        tables.iter().map(|table| table.to_string()).collect()
+    }
+
+    #[test]
+    fn explicit_entries_beat_rules() {
+        let mut outside_types = outside_types(
+            &[("users", "owner_id", "OwnerId")],
+            vec![field_rule("*_id", "{1}Id", None)],
+        );
+        let tables = tables(&["users"]);
+        assert_eq!(
+            outside_types.find(&["users"], "owner_id", "String", &tables),
+            Some(module("OwnerId"))
+        );
+        assert_eq!(
+            outside_types.find(&["users"], "post_id", "String", &tables),
+            Some(module("PostId"))
+        );
+    }
+
+    #[test]
+    fn explicit_entries_of_any_candidate_beat_rules() {
+        let mut outside_types = outside_types(
+            &[("users", "owner_id", "OwnerId")],
+            vec![field_rule("*_id", "{1}Id", None)],
+        );
+        assert_eq!(
+            outside_types.find(
+                &["users_insert_input", "users"],
+                "owner_id",
+                "String",
+                &tables(&["users"])
+            ),
+            Some(module("OwnerId"))
+        );
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let mut outside_types = outside_types(
+            &[],
+            vec![
+                field_rule("id", "UserId", None),
+                field_rule("*id", "{1}Id", None),
+            ],
+        );
+        assert_eq!(
+            outside_types.find(&["users"], "id", "String", &tables(&["users"])),
+            Some(module("UserId"))
+        );
+    }
 
-fn mocked_id_module(module: &Mod) -> String {
-    format!(
-        "module {} ({}) where
+    #[test]
+    fn rules_only_apply_when_table_exists() {
+        let mut outside_types = outside_types(&[], vec![field_rule("*_id", "{1}Id", Some("{1}s"))]);
+        assert_eq!(
+            outside_types.find(
+                &["posts"],
+                "user_id",
+                "String",
+                &tables(&["posts", "users"])
+            ),
+            Some(module("UserId"))
+        );
+        assert_eq!(
+            outside_types.find(
+                &["posts"],
+                "author_id",
+                "String",
+                &tables(&["posts", "users"])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn rules_only_apply_to_matching_tables() {
+        let mut rule = field_rule("*_id", "{1}Id", None);
+        rule.tables = "dr_*".to_string();
+        let mut outside_types = outside_types(&[], vec![rule]);
+        let tables = tables(&["dr_forms", "users"]);
+        assert_eq!(
+            outside_types.find(&["dr_forms"], "user_id", "String", &tables),
+            Some(module("UserId"))
+        );
+        assert_eq!(
+            outside_types.find(&["users"], "user_id", "String", &tables),
+            None
+        );
+    }
+
+    #[test]
+    fn rules_skip_types_that_are_not_tables() {
+        let mut outside_types = outside_types(&[], vec![field_rule("*_id", "{1}Id", None)]);
+        let tables = tables(&["users"]);
+        assert_eq!(
+            outside_types.find(&["users_max_fields"], "user_id", "String", &tables),
+            None
+        );
+        assert_eq!(
+            outside_types.find(&["users_max_fields", "users"], "user_id", "String", &tables),
+            Some(module("UserId"))
+        );
+    }
 
-newtype {} = {} String",
-        module.import, module.name, module.name, module.name
-    )
+    #[test]
+    fn modules_skip_rule_types_that_use_captures() {
+        let mut name_capture = field_rule("*_id", "UserId", None);
+        if let RuleKind::Field { type_, .. } = &mut name_capture.kind {
+            type_.name = "{1}Id".to_string();
+        }
+        let outside_types = outside_types(
+            &[],
+            vec![
+                field_rule("*_id", "{1}Id", None),
+                name_capture,
+                field_rule("id", "Id", None),
+            ],
+        );
+        let modules: Vec<_> = outside_types.modules().into_keys().collect();
+        assert_eq!(
+            modules,
+            vec![("ids".to_string(), "Data.Id".to_string(), "Id".to_string())]
+        );
+    }
+
+    #[test]
+    fn set_package_only_moves_the_accepted_packages() {
+        let mut outside_types = outside_types(
+            &[("users", "id", "UserId"), ("users", "avatar", "Avatar")],
+            vec![],
+        );
+        let users = &mut outside_types.objects.get_mut("users").unwrap().types;
+        users.get_mut("avatar").unwrap().module.package = "oa-components".to_string();
+        outside_types.set_package("mocks", |package| package == "ids");
+
+        let users = &outside_types.objects["users"].types;
+        assert_eq!(users["id"].module.package, "mocks");
+        assert_eq!(users["avatar"].module.package, "oa-components");
+    }
+
+    #[test]
+    fn unused_lists_definitions_that_resolved_nothing() {
+        let mut outside_types = outside_types(
+            &[("users", "owner_id", "OwnerId")],
+            vec![field_rule("*_id", "{1}Id", None)],
+        );
+        outside_types.defined = [location(&["OwnerId"]), location(&["*_id"])].into();
+        outside_types.find(&["users"], "owner_id", "String", &tables(&["users"]));
+        assert_eq!(outside_types.unused(), vec![&location(&["*_id"])]);
+    }
 }
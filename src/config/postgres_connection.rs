@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
+    PgPool, Result,
+};
+
+/// The longest wait between connection attempts, in seconds
+const MAX_RETRY_DELAY: u64 = 60;
+
+/// How to connect to a database Postgres types are generated from, set by env vars
+pub struct PostgresConnection {
+    options: PgConnectOptions,
+    connect_timeout: Duration,
+    /// How many times to try connecting again after the first attempt fails
+    retries: u32,
+}
+
+impl PostgresConnection {
+    /// Reads the connection from a URL env var, e.g. `DATABASE_URL`, and the optional env vars
+    /// sharing its prefix, e.g. `DATABASE_SSLMODE`, or returns None if the URL isn't set
+    pub fn from_env(url_env: &str) -> Option<Self> {
+        let database_url = std::env::var(url_env).ok()?;
+        let mut options: PgConnectOptions = database_url
+            .parse()
+            .unwrap_or_else(|err| panic!("{url_env} is not a valid Postgres URL: {err}"));
+        let prefix = url_env.strip_suffix("_URL").unwrap_or(url_env);
+
+        if let Some(ssl_mode) = env_var(&format!("{prefix}_SSLMODE")) {
+            let ssl_mode: PgSslMode = ssl_mode.parse().unwrap_or_else(|_| {
+                panic!("{prefix}_SSLMODE should be one of disable, allow, prefer, require, verify-ca or verify-full.")
+            });
+            options = options.ssl_mode(ssl_mode);
+        }
+        if let Some(root_cert) = env_var(&format!("{prefix}_SSLROOTCERT")) {
+            options = options.ssl_root_cert(root_cert);
+        }
+        if let Some(statement_timeout) = env_var(&format!("{prefix}_STATEMENT_TIMEOUT")) {
+            options = options.options([("statement_timeout", statement_timeout)]);
+        }
+
+        Some(Self {
+            options,
+            connect_timeout: Duration::from_secs(
+                env_number(&format!("{prefix}_CONNECT_TIMEOUT"), "seconds").unwrap_or(30),
+            ),
+            retries: env_number(&format!("{prefix}_CONNECT_RETRIES"), "retries").unwrap_or(0)
+                as u32,
+        })
+    }
+
+    /// Connects to the database, retrying with an increasing delay.
+    /// Returns the last error if every attempt fails.
+    pub async fn connect(&self) -> Result<PgPool> {
+        let mut attempt = 0;
+        loop {
+            let result = PgPoolOptions::new()
+                .max_connections(1)
+                .acquire_timeout(self.connect_timeout)
+                .connect_with(self.options.clone())
+                .await;
+            let err = match result {
+                Ok(pool) => return Ok(pool),
+                Err(err) => err,
+            };
+
+            attempt += 1;
+            if attempt > self.retries {
+                eprintln!(
+                    "Failed to connect to Postgres database {} at {}:{} after {attempt} attempts",
+                    self.options.get_database().unwrap_or_default(),
+                    self.options.get_host(),
+                    self.options.get_port(),
+                );
+                return Err(err);
+            }
+            let delay = Duration::from_secs(2u64.saturating_pow(attempt - 1).min(MAX_RETRY_DELAY));
+            println!(
+                "Failed to connect to Postgres, retrying in {}s: {err}",
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn env_number(name: &str, unit: &str) -> Option<u64> {
+    env_var(name).map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("{name} should be a whole number of {unit}."))
+    })
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use yaml_rust2::{yaml, Yaml};
+
+use super::{parse_outside_types::OutsideTypes, workspace::WorkspaceConfig};
+
+/// Directories that never contain workspace packages
+const IGNORED_DIRS: [&str; 5] = [".spago", "node_modules", "output", "output-es", ".git"];
+
+/// Checks that every outside type's module exists in its package in the spago workspace
+/// and that the module exports the type, panicking with a list of any that don't.
+/// Packages that aren't in the workspace, such as registry packages, and mocked packages are skipped.
+pub fn validate_outside_types(
+    outside_types: &OutsideTypes,
+    workspace_config: &WorkspaceConfig,
+    is_mocked: impl Fn(&str) -> bool,
+) {
+    let Some(workspace_dir) = &workspace_config.workspace_dir else {
+        return;
+    };
+    let packages = find_packages(Path::new(workspace_dir));
+
+    // Module name -> (package name, exports)
+    let mut modules: HashMap<String, (String, Exports)> = HashMap::new();
+    for (package, dir) in packages.iter() {
+        for file in find_purs_files(&dir.join("src")) {
+            let source = fs::read_to_string(&file)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {e}", file.display()));
+            if let Some((module, exports)) = parse_module_header(&source) {
+                modules.insert(module, (package.clone(), exports));
+            }
+        }
+    }
+
+    // Check each referenced type once, reporting the first place it is used
+    let enum_libs = workspace_config.enum_libs();
+    let mut errors = vec![];
+    for ((package, import, name), defined_at) in outside_types.modules() {
+        // Generated enums and mocks don't exist until the schemas are written
+        if enum_libs.contains(&package.as_str())
+            || is_mocked(&package)
+            || !packages.contains_key(&package)
+        {
+            continue;
+        }
+        let error = match modules.get(&import) {
+            None => format!("module {import} not found in package {package}"),
+            Some((found_in, _)) if found_in != &package => {
+                format!("module {import} is in package {found_in}, not {package}")
+            }
+            Some((_, exports)) if !exports.includes(&name) => {
+                format!("module {import} does not export {name}")
+            }
+            Some(_) => continue,
+        };
+        errors.push(format!("  {error}, used at {defined_at}"));
+    }
+
+    if !errors.is_empty() {
+        panic!(
+            "Outside types reference types missing from the workspace at {workspace_dir}:\n{}",
+            errors.join("\n")
+        );
+    }
+}
+
+/// Finds the directory of every package in the workspace, keyed by package name
+fn find_packages(dir: &Path) -> BTreeMap<String, PathBuf> {
+    let mut packages = BTreeMap::new();
+    let spago_yaml = dir.join("spago.yaml");
+    if let Ok(source) = fs::read_to_string(&spago_yaml) {
+        let docs = yaml::YamlLoader::load_from_str(&source)
+            .unwrap_or_else(|e| panic!("Failed to parse {} as yaml: {e}", spago_yaml.display()));
+        if let Some(Yaml::String(name)) = docs.first().map(|doc| &doc["package"]["name"]) {
+            packages.insert(name.clone(), dir.to_path_buf());
+        }
+    }
+    for entry in sub_dirs(dir) {
+        packages.extend(find_packages(&entry));
+    }
+    packages
+}
+
+fn find_purs_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "purs"))
+        .collect();
+    for entry in sub_dirs(dir) {
+        files.extend(find_purs_files(&entry));
+    }
+    files
+}
+
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && !path
+                    .file_name()
+                    .is_some_and(|name| IGNORED_DIRS.iter().any(|ignored| name == *ignored))
+        })
+        .collect()
+}
+
+/// The names exported by a module, or None if it exports everything
+struct Exports(Option<Vec<String>>);
+
+impl Exports {
+    fn includes(&self, name: &str) -> bool {
+        match &self.0 {
+            None => true,
+            // Re-exported modules could export anything, so give them the benefit of the doubt.
+            // `(..)` exports everything too
+            Some(exports) => exports
+                .iter()
+                .any(|e| e == name || e == ".." || e.starts_with("module ")),
+        }
+    }
+}
+
+/// Parses the name and export list from a module header,
+/// e.g. `module Data.Id.UserId (UserId(..), parse) where`
+fn parse_module_header(source: &str) -> Option<(String, Exports)> {
+    let source = strip_comments(source)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let header = source.split_once("module ")?.1;
+    let (header_end, _) = header
+        .match_indices("where")
+        .find(|(i, _)| header[..*i].ends_with([' ', ')']))?;
+    let header = header[..header_end].trim();
+    let (name, exports) = match header.split_once('(') {
+        None => (header, None),
+        Some((name, exports)) => (name, Some(exports)),
+    };
+
+    let exports = exports.map(|exports| {
+        // Split the export list on top level commas, ignoring those in e.g. `Maybe(Just, Nothing)`
+        let mut names = vec![];
+        let mut depth = 0;
+        let mut current = String::new();
+        for c in exports.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                ',' if depth == 0 => names.push(std::mem::take(&mut current)),
+                _ if depth == 0 => current.push(c),
+                _ => {}
+            }
+        }
+        names.push(current);
+        names
+            .iter()
+            .map(|name| name.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .map(|name| match name.strip_prefix("class ") {
+                Some(class) => class.to_string(),
+                None => name,
+            })
+            .collect()
+    });
+
+    Some((name.trim().to_string(), Exports(exports)))
+}
+
+/// Removes `--` line comments and `{- -}` block comments, which can be nested
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('-')) => {
+                chars.next();
+                depth += 1;
+            }
+            ('-', Some('}')) if depth > 0 => {
+                chars.next();
+                depth -= 1;
+            }
+            ('-', Some('-')) if depth == 0 => while chars.next_if(|c| *c != '\n').is_some() {},
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exports(source: &str) -> (String, Option<Vec<String>>) {
+        let (name, Exports(exports)) =
+            parse_module_header(source).expect("Module header should parse.");
+        (name, exports)
+    }
+
+    fn names(names: &[&str]) -> Option<Vec<String>> {
+        Some(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    #[test]
+    fn parses_modules_without_export_lists() {
+        assert_eq!(
+            exports("module Data.Id.UserId where\n\nimport Prelude\n"),
+            ("Data.Id.UserId".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn parses_multi_line_export_lists() {
+        let source = "module Data.Id.UserId
+  ( UserId(..)
+  , class ToId
+  , parse
+  ) where
+
+import Prelude
+";
+        assert_eq!(
+            exports(source),
+            (
+                "Data.Id.UserId".to_string(),
+                names(&["UserId", "ToId", "parse"])
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_comments_inside_the_header() {
+        let source = "-- | Ids for users
+module Data.Id.UserId
+  ( UserId -- the id
+  {- , OldId -}
+  , parse
+  ) where
+";
+        assert_eq!(
+            exports(source),
+            ("Data.Id.UserId".to_string(), names(&["UserId", "parse"]))
+        );
+    }
+
+    #[test]
+    fn re_exported_modules_include_everything() {
+        let (_, exports) = parse_module_header("module Data.Ids (module Data.Id) where")
+            .expect("Module header should parse.");
+        assert!(exports.includes("UserId"));
+    }
+
+    #[test]
+    fn dot_dot_exports_everything() {
+        let (name, exports) =
+            parse_module_header("module Data.Ids (..) where").expect("Module header should parse.");
+        assert_eq!(name, "Data.Ids");
+        assert!(exports.includes("UserId"));
+    }
+
+    #[test]
+    fn ignores_nested_block_comments_inside_the_header() {
+        let source = "module Data.Id.UserId
+  ( UserId
+  {- , OldId {- nested -} , OlderId -}
+  , parse -- , unparse
+  ) where
+";
+        assert_eq!(
+            exports(source),
+            ("Data.Id.UserId".to_string(), names(&["UserId", "parse"]))
+        );
+    }
+
+    #[test]
+    fn strips_nested_block_comments() {
+        assert_eq!(strip_comments("a {- b {- c -} d -} e"), "a  e");
+        assert_eq!(strip_comments("a {- -- b -} c"), "a  c");
+        assert_eq!(strip_comments("a -- {- b\nc"), "a \nc");
+    }
+
+    #[test]
+    fn strips_line_and_block_comments() {
+        assert_eq!(
+            strip_comments("a -- line\nb {- block\nstill block -} c"),
+            "a \nb  c"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 use std::thread::Result;
 
 use hashlink::LinkedHashMap;
+use stringcase::{kebab_case, pascal_case, snake_case};
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use yaml_rust2::{yaml, Yaml};
 
+use crate::config::glob::glob_captures;
+use crate::enums::generate_enum::is_column_enum;
+use crate::enums::template::{
+    Context, Template, Value, GRAPHQL_ENUM_TEMPLATE, POSTGRES_ENUM_TEMPLATE,
+};
+
 pub async fn parse_workspace() -> Result<WorkspaceConfig> {
     let file_path: String = std::env::var("SPAGO_WORKSPACE_CONFIG_YAML")
         .expect("SPAGO_WORKSPACE_CONFIG_YAML must be set");
@@ -32,17 +39,47 @@ pub async fn parse_workspace() -> Result<WorkspaceConfig> {
 
 #[derive(Clone)]
 pub struct WorkspaceConfig {
-    pub postgres_enums_lib: String,
     pub postgres_enums_dir: String,
+    pub postgres_enums_order: EnumOrder,
+    pub postgres_enums_schemas: PostgresSchemas,
+    /// The databases Postgres types are generated from, starting with the default database
+    pub postgres_databases: Vec<PostgresDatabase>,
+    /// How enum values are documented in the comments of Postgres enum types, if at all
+    pub postgres_enum_value_docs: Option<ValueDocs>,
     pub shared_graphql_enums_lib: String,
     pub shared_graphql_enums_dir: String,
+    pub shared_graphql_enums: SharedEnumRules,
+    /// What to do when roles see different values for a shared GraphQL enum
+    pub shared_graphql_enums_mismatch: EnumMismatch,
     pub schema_libs_prefix: String,
     pub schema_libs_dir: String,
+    /// The root of the spago workspace, used to check outside types exist
+    pub workspace_dir: Option<String>,
+    /// How outside types are mocked when MOCK_OUTSIDE_TYPES is set
+    pub mock_outside_types: MockConfig,
+    /// Extra instances generated for every enum
+    pub enum_instances: Vec<EnumInstance>,
+    pub enum_templates: EnumTemplates,
+    pub enum_constructors: EnumConstructors,
+    /// Hasura enum tables to generate shared enums from
+    pub enum_tables: Vec<EnumTable>,
 }
 
 impl WorkspaceConfig {
+    /// The packages enums are generated in, which don't exist until generation
+    pub fn enum_libs(&self) -> Vec<&str> {
+        self.postgres_databases
+            .iter()
+            .map(|database| database.lib.as_str())
+            .chain([self.shared_graphql_enums_lib.as_str()])
+            .collect()
+    }
+
     fn new(yaml_hash: &LinkedHashMap<Yaml, Yaml>) -> Option<Self> {
-        let postgres_enums_lib = yaml_hash.get(&Yaml::String("postgres_enums_lib".to_string()))?;
+        let postgres_enums_lib = yaml_hash
+            .get(&Yaml::String("postgres_enums_lib".to_string()))?
+            .as_str()
+            .expect("Workspace yaml should contain postgres_enums_lib key.");
         let postgres_enums_dir = yaml_hash.get(&Yaml::String("postgres_enums_dir".to_string()))?;
         let shared_graphql_enums_lib =
             yaml_hash.get(&Yaml::String("shared_graphql_enums_lib".to_string()))?;
@@ -50,16 +87,47 @@ impl WorkspaceConfig {
             yaml_hash.get(&Yaml::String("shared_graphql_enums_dir".to_string()))?;
         let schema_libs_prefix = yaml_hash.get(&Yaml::String("schema_libs_prefix".to_string()))?;
         let schema_libs_dir = yaml_hash.get(&Yaml::String("schema_libs_dir".to_string()))?;
+        let workspace_dir = yaml_hash
+            .get(&Yaml::String("workspace_dir".to_string()))
+            .map(|dir| {
+                dir.as_str()
+                    .expect("Workspace yaml workspace_dir key should be a string.")
+                    .to_string()
+            });
 
         Some(Self {
-            postgres_enums_lib: postgres_enums_lib
-                .as_str()
-                .expect("Workspace yaml should contain postgres_enums_lib key.")
-                .to_string(),
+            postgres_databases: std::iter::once(PostgresDatabase::default(postgres_enums_lib))
+                .chain(
+                    match yaml_hash.get(&Yaml::String("postgres_databases".to_string())) {
+                        None => vec![],
+                        Some(Yaml::Array(databases)) => databases
+                            .iter()
+                            .map(|database| PostgresDatabase::new(database, postgres_enums_lib))
+                            .collect(),
+                        Some(_) => panic!("Workspace yaml postgres_databases key should be a list."),
+                    },
+                )
+                .collect(),
             postgres_enums_dir: postgres_enums_dir
                 .as_str()
                 .expect("Workspace yaml should contain postgres_enums_dir key.")
                 .to_string(),
+            postgres_enums_schemas: PostgresSchemas::new(
+                yaml_hash.get(&Yaml::String("postgres_enums_schemas".to_string())),
+            ),
+            postgres_enums_order: match yaml_hash
+                .get(&Yaml::String("postgres_enums_order".to_string()))
+                .map(|order| order.as_str())
+            {
+                None | Some(Some("declaration")) => EnumOrder::Declaration,
+                Some(Some("alphabetical")) => EnumOrder::Alphabetical,
+                Some(_) => panic!(
+                    "Workspace yaml postgres_enums_order key should be 'declaration' or 'alphabetical'."
+                ),
+            },
+            postgres_enum_value_docs: ValueDocs::new(
+                yaml_hash.get(&Yaml::String("postgres_enum_value_docs".to_string())),
+            ),
             shared_graphql_enums_lib: shared_graphql_enums_lib
                 .as_str()
                 .expect("Workspace yaml should contain shared_graphql_enums_lib key.")
@@ -68,6 +136,19 @@ impl WorkspaceConfig {
                 .as_str()
                 .expect("Workspace yaml should contain shared_graphql_enums_dir key.")
                 .to_string(),
+            shared_graphql_enums: SharedEnumRules::new(
+                yaml_hash.get(&Yaml::String("shared_graphql_enums".to_string())),
+            ),
+            shared_graphql_enums_mismatch: match yaml_hash
+                .get(&Yaml::String("shared_graphql_enums_mismatch".to_string()))
+                .map(|mismatch| mismatch.as_str())
+            {
+                None | Some(Some("merge")) => EnumMismatch::Merge,
+                Some(Some("fail")) => EnumMismatch::Fail,
+                Some(_) => panic!(
+                    "Workspace yaml shared_graphql_enums_mismatch key should be 'merge' or 'fail'."
+                ),
+            },
             schema_libs_prefix: schema_libs_prefix
                 .as_str()
                 .expect("Workspace yaml should contain schema_libs_prefix key.")
@@ -76,6 +157,727 @@ impl WorkspaceConfig {
                 .as_str()
                 .expect("Workspace yaml should contain schema_libs_dir key.")
                 .to_string(),
+            workspace_dir,
+            mock_outside_types: MockConfig::new(
+                yaml_hash.get(&Yaml::String("mock_outside_types".to_string())),
+                shared_graphql_enums_dir
+                    .as_str()
+                    .expect("Workspace yaml should contain shared_graphql_enums_dir key."),
+            ),
+            enum_instances: match yaml_hash.get(&Yaml::String("enum_instances".to_string())) {
+                None => vec![],
+                Some(Yaml::Array(instances)) => instances.iter().map(EnumInstance::new).collect(),
+                Some(_) => panic!("Workspace yaml enum_instances key should be a list."),
+            },
+            enum_templates: EnumTemplates::new(
+                yaml_hash.get(&Yaml::String("enum_templates".to_string())),
+            ),
+            enum_constructors: EnumConstructors::new(
+                yaml_hash.get(&Yaml::String("enum_constructors".to_string())),
+            ),
+            enum_tables: match yaml_hash.get(&Yaml::String("enum_tables".to_string())) {
+                None => vec![],
+                Some(Yaml::Array(tables)) => tables.iter().map(EnumTable::new).collect(),
+                Some(_) => panic!("Workspace yaml enum_tables key should be a list."),
+            },
+        })
+    }
+}
+
+/// An instance of a class from outside the enum packages' default dependencies.
+/// The generated GraphQL and Postgres enum modules import different helpers,
+/// so the instance body is given for each kind of enum it should be generated for.
+/// Bodies are templates, rendered with the same values as the enum templates.
+#[derive(Clone)]
+pub struct EnumInstance {
+    pub class: String,
+    pub module: String,
+    pub package: String,
+    /// The instance body for shared GraphQL enums
+    pub graphql: Option<Template>,
+    /// The instance body for Postgres enums
+    pub postgres: Option<Template>,
+}
+
+impl EnumInstance {
+    fn new(yaml: &Yaml) -> Self {
+        let get_str = |key: &str| match &yaml[key] {
+            Yaml::BadValue => None,
+            value => Some(
+                value
+                    .as_str()
+                    .unwrap_or_else(|| {
+                        panic!("Workspace yaml enum_instances.{key} keys should be strings.")
+                    })
+                    .trim()
+                    .to_string(),
+            ),
+        };
+        let required = |key: &str| {
+            get_str(key).unwrap_or_else(|| {
+                panic!("Workspace yaml enum_instances entries should have a {key} key.")
+            })
+        };
+        let class = required("class");
+        // `body` is shorthand for using the same body for both kinds of enum
+        let body = get_str("body");
+        let template = |key: &str| {
+            let source = get_str(key).or(body.clone())?;
+            Some(Template::parse(
+                &format!("enum_instances {class} {key} body"),
+                &source,
+            ))
+        };
+        Self {
+            graphql: template("graphql"),
+            postgres: template("postgres"),
+            module: required("module"),
+            package: required("package"),
+            class,
+        }
+    }
+
+    pub fn import(&self) -> String {
+        format!("import {} (class {})", self.module, self.class)
+    }
+
+    /// Renders the instance for an enum from one of its bodies
+    pub fn instance(&self, body: &Template, context: &Context) -> String {
+        let name = match context.get("name") {
+            Some(Value::Text(name)) => name,
+            _ => panic!("Enum template context should have a name."),
+        };
+        format!(
+            "instance {} {name} where\n  {}",
+            self.class,
+            body.render(context)
+                .lines()
+                .collect::<Vec<&str>>()
+                .join("\n  ")
+        )
+    }
+}
+
+/// What to do when roles see different values for a shared GraphQL enum
+#[derive(Clone, Copy)]
+pub enum EnumMismatch {
+    /// Generates every value seen by any role
+    Merge,
+    Fail,
+}
+
+/// The order of a Postgres enum's constructors
+#[derive(Clone, Copy)]
+pub enum EnumOrder {
+    /// The order the labels were declared in the database
+    Declaration,
+    /// Alphabetical order, ignoring case
+    Alphabetical,
+}
+
+/// Postgres' own schemas, which never have app enums
+const SYSTEM_SCHEMAS: [&str; 2] = ["pg_catalog", "information_schema"];
+
+/// The Postgres schemas to generate enums from
+#[derive(Clone)]
+pub struct PostgresSchemas {
+    /// Only these schemas are included when set, otherwise every schema is
+    pub include: Option<Vec<String>>,
+    pub exclude: Vec<String>,
+}
+
+impl PostgresSchemas {
+    fn new(yaml: Option<&Yaml>) -> Self {
+        let yaml = match yaml {
+            None => &Yaml::BadValue,
+            Some(yaml @ Yaml::Hash(_)) => yaml,
+            Some(_) => panic!("Workspace yaml postgres_enums_schemas key should be a hash."),
+        };
+        let schemas = |key: &str| {
+            match &yaml[key] {
+            Yaml::BadValue => None,
+            Yaml::Array(schemas) => Some(
+                schemas
+                    .iter()
+                    .map(|schema| {
+                        schema
+                            .as_str()
+                            .unwrap_or_else(|| {
+                                panic!("Workspace yaml postgres_enums_schemas.{key} should be a list of schema names.")
+                            })
+                            .to_string()
+                    })
+                    .collect::<Vec<String>>(),
+            ),
+            _ => panic!("Workspace yaml postgres_enums_schemas.{key} key should be a list."),
+        }
+        };
+
+        let mut exclude = schemas("exclude").unwrap_or_default();
+        exclude.extend(SYSTEM_SCHEMAS.iter().map(|schema| schema.to_string()));
+        Self {
+            include: schemas("include"),
+            exclude,
+        }
+    }
+}
+
+/// The instances that can be listed by name in `mock_outside_types.instances`,
+/// as `(class, module, package)`. All of them are derived by default.
+const MOCK_INSTANCES: [(&str, &str, &str); 8] = [
+    ("Eq", "Prelude", "prelude"),
+    ("Ord", "Prelude", "prelude"),
+    ("Show", "Prelude", "prelude"),
+    ("EncodeJson", "Data.Argonaut.Encode", "argonaut-codecs"),
+    ("DecodeJson", "Data.Argonaut.Decode", "argonaut-codecs"),
+    (
+        "GqlArgString",
+        "GraphQL.Client.ToGqlString",
+        "graphql-client",
+    ),
+    ("EncodeHasura", "GraphQL.Hasura.Encode", "graphql-client"),
+    ("DecodeHasura", "GraphQL.Hasura.Decode", "graphql-client"),
+];
+
+#[derive(Clone)]
+pub struct MockConfig {
+    /// Names or globs of the outside type packages to mock, leaving types in other packages as they are
+    packages: Vec<String>,
+    /// The package to write every mock to, otherwise each mock is written to its type's own package
+    pub package: Option<String>,
+    /// The directory the mock packages are written to
+    pub dir: String,
+    /// The instances derived for every mock
+    pub instances: Vec<MockInstance>,
+}
+
+/// A class with a newtype derived instance for every mock
+#[derive(Clone)]
+pub struct MockInstance {
+    pub class: String,
+    pub module: String,
+    pub package: String,
+}
+
+impl MockConfig {
+    fn new(yaml: Option<&Yaml>, shared_graphql_enums_dir: &str) -> Self {
+        let yaml = match yaml {
+            None => &Yaml::BadValue,
+            Some(yaml @ Yaml::Hash(_)) => yaml,
+            Some(_) => panic!("Workspace yaml mock_outside_types key should be a hash."),
+        };
+        let get_str = |key: &str| match &yaml[key] {
+            Yaml::BadValue => None,
+            value => Some(
+                value
+                    .as_str()
+                    .unwrap_or_else(|| {
+                        panic!("Workspace yaml mock_outside_types.{key} key should be a string.")
+                    })
+                    .to_string(),
+            ),
+        };
+
+        let instances = match &yaml["instances"] {
+            Yaml::BadValue => MOCK_INSTANCES
+                .iter()
+                .map(|(class, module, package)| MockInstance {
+                    class: class.to_string(),
+                    module: module.to_string(),
+                    package: package.to_string(),
+                })
+                .collect(),
+            Yaml::Array(instances) => instances.iter().map(MockInstance::new).collect(),
+            _ => panic!("Workspace yaml mock_outside_types.instances key should be a list."),
+        };
+
+        let packages = match &yaml["packages"] {
+            Yaml::BadValue => vec![],
+            Yaml::Array(packages) => packages
+                .iter()
+                .map(|package| {
+                    package
+                        .as_str()
+                        .unwrap_or_else(|| {
+                            panic!("Workspace yaml mock_outside_types.packages should be a list of package names or globs.")
+                        })
+                        .to_string()
+                })
+                .collect(),
+            _ => panic!("Workspace yaml mock_outside_types.packages key should be a list."),
+        };
+
+        Self {
+            packages,
+            package: get_str("package"),
+            dir: get_str("dir").unwrap_or(shared_graphql_enums_dir.to_string()),
+            instances,
+        }
+    }
+}
+
+impl MockConfig {
+    /// Whether types in a package are mocked, either because the package is listed in `packages`
+    /// or because it is the package every mock is written to
+    pub fn mocks(&self, package: &str) -> bool {
+        self.package.as_deref() == Some(package)
+            || self
+                .packages
+                .iter()
+                .any(|pattern| glob_captures(pattern, package).is_some())
+    }
+}
+
+impl MockInstance {
+    /// Parses either the name of a known class, e.g. `Eq`,
+    /// or a `{class, module, package}` hash for any other class
+    fn new(yaml: &Yaml) -> Self {
+        if let Some(class) = yaml.as_str() {
+            let (class, module, package) = MOCK_INSTANCES
+                .iter()
+                .find(|(known, _, _)| *known == class)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Unknown mock instance {class}. Use one of {} or give its class, module and package.",
+                        MOCK_INSTANCES.map(|(known, _, _)| known).join(", ")
+                    )
+                });
+            return Self {
+                class: class.to_string(),
+                module: module.to_string(),
+                package: package.to_string(),
+            };
+        }
+        let get_str = |key: &str| {
+            yaml[key]
+                .as_str()
+                .unwrap_or_else(|| {
+                    panic!("Workspace yaml mock_outside_types.instances entries should have a string {key} key.")
+                })
+                .to_string()
+        };
+        Self {
+            class: get_str("class"),
+            module: get_str("module"),
+            package: get_str("package"),
+        }
+    }
+}
+
+/// The templates the enum modules are rendered with
+#[derive(Clone)]
+pub struct EnumTemplates {
+    pub graphql: Template,
+    pub postgres: Template,
+    /// Packages the templates import from, on top of the enum packages' default dependencies
+    pub dependencies: Vec<String>,
+}
+
+impl EnumTemplates {
+    fn new(yaml: Option<&Yaml>) -> Self {
+        let yaml = match yaml {
+            None => &Yaml::BadValue,
+            Some(yaml @ Yaml::Hash(_)) => yaml,
+            Some(_) => panic!("Workspace yaml enum_templates key should be a hash."),
+        };
+        let template = |key: &str, default: &str| match &yaml[key] {
+            Yaml::BadValue => Template::parse(&format!("default {key} template"), default),
+            Yaml::String(file) => {
+                let source = std::fs::read_to_string(file)
+                    .unwrap_or_else(|e| panic!("Failed to read enum template {file}: {e}"));
+                Template::parse(file, &source)
+            }
+            _ => panic!("Workspace yaml enum_templates.{key} key should be a file path."),
+        };
+        let dependencies = match &yaml["dependencies"] {
+            Yaml::BadValue => vec![],
+            Yaml::Array(dependencies) => dependencies
+                .iter()
+                .map(|dependency| {
+                    dependency
+                        .as_str()
+                        .expect(
+                            "Workspace yaml enum_templates.dependencies should be package names.",
+                        )
+                        .to_string()
+                })
+                .collect(),
+            _ => panic!("Workspace yaml enum_templates.dependencies key should be a list."),
+        };
+
+        Self {
+            graphql: template("graphql", GRAPHQL_ENUM_TEMPLATE),
+            postgres: template("postgres", POSTGRES_ENUM_TEMPLATE),
+            dependencies,
+        }
+    }
+}
+
+/// A Hasura enum table, whose rows are generated as an enum shared by every schema
+#[derive(Clone)]
+pub struct EnumTable {
+    pub schema: String,
+    pub table: String,
+    /// The name of the GraphQL enum Hasura generates for the table
+    pub graphql_name: String,
+    pub value_column: String,
+    /// The column documenting each value, which is optional in the table
+    pub comment_column: String,
+}
+
+impl EnumTable {
+    /// Parses either a table name, e.g. `payment_types` or `audit.payment_types`,
+    /// or a hash with a `table` key and optional `graphql_name`, `value_column` and `comment_column` keys
+    fn new(yaml: &Yaml) -> Self {
+        let get_str = |key: &str| match &yaml[key] {
+            Yaml::BadValue => None,
+            value => Some(
+                value
+                    .as_str()
+                    .unwrap_or_else(|| {
+                        panic!("Workspace yaml enum_tables.{key} keys should be strings.")
+                    })
+                    .to_string(),
+            ),
+        };
+        let qualified_table = match yaml {
+            Yaml::String(table) => table.clone(),
+            Yaml::Hash(_) => get_str("table")
+                .expect("Workspace yaml enum_tables entries should have a table key."),
+            _ => panic!("Workspace yaml enum_tables entries should be a table name or a hash."),
+        };
+        let (schema, table) = match qualified_table.split_once('.') {
+            Some((schema, table)) => (schema.to_string(), table.to_string()),
+            None => ("public".to_string(), qualified_table.clone()),
+        };
+        // Hasura prefixes the names of tables outside the public schema with their schema
+        let graphql_name = get_str("graphql_name").unwrap_or_else(|| match schema.as_str() {
+            "public" => format!("{table}_enum"),
+            _ => format!("{schema}_{table}_enum"),
+        });
+
+        Self {
+            graphql_name,
+            value_column: get_str("value_column").unwrap_or("value".to_string()),
+            comment_column: get_str("comment_column").unwrap_or("comment".to_string()),
+            schema,
+            table,
+        }
+    }
+}
+
+/// How the values of a Postgres enum are documented in the enum type's comment,
+/// as a line pattern with `{value}` and `{doc}` placeholders, e.g. `{value}: {doc}`.
+/// Postgres can't comment on enum values themselves.
+#[derive(Clone)]
+pub struct ValueDocs {
+    /// The text before `{value}`
+    prefix: String,
+    /// The text between `{value}` and `{doc}`
+    separator: String,
+}
+
+impl ValueDocs {
+    fn new(yaml: Option<&Yaml>) -> Option<Self> {
+        let pattern = match yaml {
+            None => "{value}: {doc}",
+            Some(Yaml::Boolean(false)) => return None,
+            Some(Yaml::String(pattern)) => pattern,
+            Some(_) => {
+                panic!("Workspace yaml postgres_enum_value_docs key should be a string or false.")
+            }
+        };
+        let (prefix, rest) = pattern.split_once("{value}").unwrap_or_else(|| {
+            panic!("Workspace yaml postgres_enum_value_docs should contain {{value}}.")
+        });
+        let separator = rest.strip_suffix("{doc}").unwrap_or_else(|| {
+            panic!("Workspace yaml postgres_enum_value_docs should end with {{doc}}.")
+        });
+        Some(Self {
+            prefix: prefix.trim_start().to_string(),
+            separator: separator.to_string(),
         })
     }
+
+    /// Splits an enum type's comment into the docs of the type and of each of its values.
+    /// Lines matching the pattern for one of the values document that value,
+    /// and every other line documents the type.
+    pub fn split(&self, comment: &str, values: &[String]) -> (Option<String>, Vec<Option<String>>) {
+        let mut type_doc: Vec<&str> = vec![];
+        let mut value_docs: Vec<Vec<&str>> = vec![vec![]; values.len()];
+        for line in comment.lines() {
+            let value_doc = line
+                .trim_start()
+                .strip_prefix(&self.prefix)
+                .and_then(|rest| {
+                    values.iter().enumerate().find_map(|(i, value)| {
+                        let doc = rest
+                            .strip_prefix(value.as_str())?
+                            .strip_prefix(&self.separator)?;
+                        Some((i, doc))
+                    })
+                });
+            match value_doc {
+                Some((i, doc)) => value_docs[i].push(doc.trim()),
+                None => type_doc.push(line),
+            }
+        }
+
+        let join = |lines: &[&str]| match lines.join("\n").trim() {
+            "" => None,
+            doc => Some(doc.to_string()),
+        };
+        (
+            join(&type_doc),
+            value_docs.iter().map(|lines| join(lines)).collect(),
+        )
+    }
+}
+
+/// How enum labels are turned into constructor names
+#[derive(Clone, Copy)]
+pub enum ConstructorCase {
+    /// Upper cases the first letter, e.g. `in_progress` becomes `In_progress`
+    FirstUpper,
+    /// e.g. `in_progress` becomes `InProgress`
+    Pascal,
+    /// e.g. `in-progress` becomes `IN_PROGRESS`
+    ScreamingSnake,
+}
+
+impl ConstructorCase {
+    fn new(yaml: &Yaml, key: &str, default: Self) -> Self {
+        match yaml[key].as_str() {
+            None if yaml[key].is_badvalue() => default,
+            Some("first_upper") => Self::FirstUpper,
+            Some("pascal") => Self::Pascal,
+            Some("screaming_snake") => Self::ScreamingSnake,
+            _ => panic!(
+                "Workspace yaml enum_constructors.{key} key should be 'first_upper', 'pascal' or 'screaming_snake'."
+            ),
+        }
+    }
+}
+
+/// How the constructors of generated enums are named
+#[derive(Clone)]
+pub struct EnumConstructors {
+    pub graphql: ConstructorCase,
+    pub postgres: ConstructorCase,
+    /// Prefixes constructors that wouldn't start with an upper case letter, e.g. labels starting with digits
+    pub prefix: String,
+}
+
+impl EnumConstructors {
+    fn new(yaml: Option<&Yaml>) -> Self {
+        let yaml = match yaml {
+            None => &Yaml::BadValue,
+            Some(yaml @ Yaml::Hash(_)) => yaml,
+            Some(_) => panic!("Workspace yaml enum_constructors key should be a hash."),
+        };
+        let prefix = match &yaml["prefix"] {
+            Yaml::BadValue => "V".to_string(),
+            Yaml::String(prefix) if prefix.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                prefix.clone()
+            }
+            _ => panic!(
+                "Workspace yaml enum_constructors.prefix key should start with an upper case letter."
+            ),
+        };
+        Self {
+            graphql: ConstructorCase::new(yaml, "graphql", ConstructorCase::FirstUpper),
+            postgres: ConstructorCase::new(yaml, "postgres", ConstructorCase::ScreamingSnake),
+            prefix,
+        }
+    }
+}
+
+/// Which GraphQL enums are generated once in the shared GraphQL enums package
+/// rather than as variants in each role's schema
+#[derive(Clone)]
+pub struct SharedEnumRules {
+    /// Names or globs of enums to share, matched against both the GraphQL name and the purescript type name
+    include: Vec<String>,
+    /// Names or globs of enums to never share, which take precedence over every other rule
+    exclude: Vec<String>,
+    /// Shares enums that every role sees with the same values
+    pub identical: bool,
+}
+
+impl SharedEnumRules {
+    /// Parses the `shared_graphql_enums` key, along with the suffixes in the `SHARED_ENUM_SUFFIXES` env var
+    fn new(yaml: Option<&Yaml>) -> Self {
+        let yaml = match yaml {
+            None => &Yaml::BadValue,
+            Some(yaml @ Yaml::Hash(_)) => yaml,
+            Some(_) => panic!("Workspace yaml shared_graphql_enums key should be a hash."),
+        };
+        let patterns = |key: &str| {
+            match &yaml[key] {
+            Yaml::BadValue => vec![],
+            Yaml::Array(patterns) => patterns
+                .iter()
+                .map(|pattern| {
+                    pattern
+                        .as_str()
+                        .unwrap_or_else(|| {
+                            panic!("Workspace yaml shared_graphql_enums.{key} should be a list of enum names or globs.")
+                        })
+                        .to_string()
+                })
+                .collect(),
+            _ => panic!("Workspace yaml shared_graphql_enums.{key} key should be a list."),
+        }
+        };
+
+        let mut include = patterns("include");
+        if let Ok(suffixes) = std::env::var("SHARED_ENUM_SUFFIXES") {
+            include.extend(
+                suffixes
+                    .split(',')
+                    .filter(|suffix| !suffix.is_empty())
+                    .map(|suffix| format!("*{suffix}")),
+            );
+        }
+        Self {
+            include,
+            exclude: patterns("exclude"),
+            identical: match &yaml["identical"] {
+                Yaml::BadValue => false,
+                Yaml::Boolean(identical) => *identical,
+                _ => {
+                    panic!("Workspace yaml shared_graphql_enums.identical key should be a boolean.")
+                }
+            },
+        }
+    }
+
+    /// Whether a GraphQL enum is shared, given whether every role sees it with the same values.
+    /// Column enums are only shared when included, as shared enums don't get their row helpers.
+    pub fn is_shared(&self, name: &str, identical: bool) -> bool {
+        let type_name = pascal_case(name);
+        let matches = |patterns: &Vec<String>| {
+            patterns.iter().any(|pattern| {
+                glob_captures(pattern, name).is_some()
+                    || glob_captures(pattern, &type_name).is_some()
+            })
+        };
+        !matches(&self.exclude)
+            && (matches(&self.include) || (self.identical && identical && !is_column_enum(name)))
+    }
+}
+
+/// A database Postgres types are generated from, each into its own package
+#[derive(Clone)]
+pub struct PostgresDatabase {
+    /// None for the default database at `DATABASE_URL`, which enum tables are read from
+    pub name: Option<String>,
+    /// The env var holding the database's URL
+    pub url_env: String,
+    pub lib: String,
+    /// The prefix of the generated modules, e.g. `OaEnumsAnalytics`
+    pub module_prefix: String,
+    /// The prefix Hasura gives the types of the database's source, e.g. `analytics_`
+    pub type_prefix: String,
+}
+
+impl PostgresDatabase {
+    fn default(postgres_enums_lib: &str) -> Self {
+        Self {
+            name: None,
+            url_env: "DATABASE_URL".to_string(),
+            lib: postgres_enums_lib.to_string(),
+            module_prefix: pascal_case(postgres_enums_lib),
+            type_prefix: String::new(),
+        }
+    }
+
+    /// Parses a hash with a `name` key and optional `url_env`, `lib`, `module_prefix` and `type_prefix` keys
+    fn new(yaml: &Yaml, postgres_enums_lib: &str) -> Self {
+        let get_str = |key: &str| match &yaml[key] {
+            Yaml::BadValue => None,
+            value => Some(
+                value
+                    .as_str()
+                    .unwrap_or_else(|| {
+                        panic!("Workspace yaml postgres_databases.{key} keys should be strings.")
+                    })
+                    .to_string(),
+            ),
+        };
+        let name = match yaml {
+            Yaml::Hash(_) => get_str("name")
+                .expect("Workspace yaml postgres_databases entries should have a name key."),
+            _ => panic!("Workspace yaml postgres_databases entries should be hashes."),
+        };
+        let lib =
+            get_str("lib").unwrap_or_else(|| format!("{postgres_enums_lib}-{}", kebab_case(&name)));
+
+        Self {
+            url_env: get_str("url_env")
+                .unwrap_or_else(|| format!("{}_DATABASE_URL", snake_case(&name).to_uppercase())),
+            module_prefix: get_str("module_prefix").unwrap_or_else(|| pascal_case(&lib)),
+            type_prefix: get_str("type_prefix").unwrap_or_default(),
+            name: Some(name),
+            lib,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(include: &[&str], exclude: &[&str], identical: bool) -> SharedEnumRules {
+        SharedEnumRules {
+            include: include.iter().map(|pattern| pattern.to_string()).collect(),
+            exclude: exclude.iter().map(|pattern| pattern.to_string()).collect(),
+            identical,
+        }
+    }
+
+    #[test]
+    fn shares_included_enums_by_graphql_or_type_name() {
+        let rules = rules(&["order_by", "*Enum"], &[], false);
+        assert!(rules.is_shared("order_by", false));
+        assert!(rules.is_shared("payment_types_enum", false));
+        assert!(!rules.is_shared("users_select_column", false));
+    }
+
+    #[test]
+    fn exclude_takes_precedence() {
+        let rules = rules(&["*_enum"], &["secret_*", "AuditEnum"], true);
+        assert!(!rules.is_shared("secret_types_enum", true));
+        assert!(!rules.is_shared("audit_enum", true));
+        assert!(rules.is_shared("payment_types_enum", true));
+    }
+
+    #[test]
+    fn shares_identical_enums_only_when_enabled() {
+        assert!(rules(&[], &[], true).is_shared("cursor_ordering", true));
+        assert!(!rules(&[], &[], true).is_shared("cursor_ordering", false));
+        assert!(!rules(&[], &[], false).is_shared("cursor_ordering", true));
+    }
+
+    #[test]
+    fn shares_identical_column_enums_only_when_included() {
+        assert!(!rules(&[], &[], true).is_shared("users_select_column", true));
+        assert!(!rules(&[], &[], true).is_shared("users_pkey_constraint", true));
+        assert!(rules(&["*_select_column"], &[], true).is_shared("users_select_column", true));
+    }
+
+    #[test]
+    fn mocks_listed_packages_and_the_mock_package() {
+        let config = MockConfig {
+            packages: vec!["oa-ids".to_string(), "oa-*-ids".to_string()],
+            package: Some("mock-ids".to_string()),
+            dir: String::new(),
+            instances: vec![],
+        };
+        assert!(config.mocks("oa-ids"));
+        assert!(config.mocks("oa-user-ids"));
+        assert!(config.mocks("mock-ids"));
+        assert!(!config.mocks("oa-components"));
+        assert!(!config.mocks("graphql-client"));
+    }
 }
@@ -0,0 +1,206 @@
+use std::{collections::HashMap, fmt};
+
+use yaml_rust2::{
+    parser::{MarkedEventReceiver, Parser},
+    scanner::Marker,
+    Event,
+};
+
+/// The source positions of every value in a yaml document, keyed by the path of keys
+/// (or array indices) leading to it, so config errors can point at the offending line.
+pub struct YamlPositions {
+    file: String,
+    markers: HashMap<Vec<String>, Marker>,
+}
+
+impl YamlPositions {
+    pub fn load(file: &str, source: &str) -> Self {
+        let mut receiver = PositionReceiver {
+            path: vec![],
+            containers: vec![],
+            markers: HashMap::new(),
+        };
+        Parser::new(source.chars())
+            .load(&mut receiver, false)
+            .unwrap_or_else(|e| panic!("Failed to parse {file} as yaml: {e}"));
+        Self {
+            file: file.to_string(),
+            markers: receiver.markers,
+        }
+    }
+
+    /// Finds where the value at `path` is defined
+    pub fn at(&self, path: &[&str]) -> Location {
+        let path: Vec<String> = path.iter().map(|key| key.to_string()).collect();
+        let (line, column) = match self.markers.get(&path) {
+            Some(marker) => (marker.line(), marker.col() + 1),
+            None => (0, 0),
+        };
+        Location {
+            file: self.file.clone(),
+            line,
+            column,
+            path: path.join("."),
+        }
+    }
+}
+
+/// Where a value was defined in a yaml file.
+/// Displays as e.g. `outside_types.yaml line 12 column 9 (outside_types.users.id)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    file: String,
+    line: usize,
+    column: usize,
+    path: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{} ({})", self.file, self.path),
+            line => write!(
+                f,
+                "{} line {line} column {} ({})",
+                self.file, self.column, self.path
+            ),
+        }
+    }
+}
+
+enum Container {
+    /// A mapping, which is expecting a key when `None` and a value for that key otherwise,
+    /// holding where the key is so the value can be reported there
+    Mapping(Option<(String, Marker)>),
+    Sequence(usize),
+}
+
+struct PositionReceiver {
+    path: Vec<String>,
+    containers: Vec<Container>,
+    markers: HashMap<Vec<String>, Marker>,
+}
+
+impl PositionReceiver {
+    /// Records the position of a value and returns the path to it,
+    /// or returns None if the event was a mapping key.
+    /// Mapping values are recorded at their key, as nested mappings and block scalars
+    /// would otherwise point past the line that defines them.
+    fn value(&mut self, key: &str, mut mark: Marker) -> Option<Vec<String>> {
+        let mut path = self.path.clone();
+        match self.containers.last_mut() {
+            Some(Container::Mapping(current @ None)) => {
+                *current = Some((key.to_string(), mark));
+                return None;
+            }
+            Some(Container::Mapping(current)) => {
+                let (key, key_mark) = current.take().expect("Mapping value should have a key.");
+                path.push(key);
+                mark = key_mark;
+            }
+            Some(Container::Sequence(index)) => {
+                path.push(index.to_string());
+                *index += 1;
+            }
+            None => {}
+        }
+        self.markers.insert(path.clone(), mark);
+        Some(path)
+    }
+}
+
+impl MarkedEventReceiver for PositionReceiver {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::Scalar(value, ..) => {
+                self.value(&value, mark);
+            }
+            Event::Alias(_) => {
+                self.value("", mark);
+            }
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                if let Some(path) = self.value("", mark) {
+                    self.path = path;
+                }
+                self.containers.push(match ev {
+                    Event::MappingStart(..) => Container::Mapping(None),
+                    _ => Container::Sequence(0),
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.containers.pop();
+                self.path.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "outside_types:
+  users:
+    id: UserId, Data.Ids, ids
+    tags:
+      - TagId, Data.Ids, ids
+types:
+  id: Id, Data.Ids, ids
+  note: >
+    Folded text
+    over two lines
+";
+
+    #[test]
+    fn locates_scalar_values() {
+        let positions = YamlPositions::load("outside.yaml", SOURCE);
+        assert_eq!(
+            positions.at(&["types", "id"]).to_string(),
+            "outside.yaml line 7 column 3 (types.id)"
+        );
+    }
+
+    #[test]
+    fn locates_nested_values() {
+        let positions = YamlPositions::load("outside.yaml", SOURCE);
+        assert_eq!(
+            positions.at(&["outside_types", "users", "id"]).to_string(),
+            "outside.yaml line 3 column 5 (outside_types.users.id)"
+        );
+        assert_eq!(
+            positions
+                .at(&["outside_types", "users", "tags", "0"])
+                .to_string(),
+            "outside.yaml line 5 column 9 (outside_types.users.tags.0)"
+        );
+    }
+
+    #[test]
+    fn locates_block_values_at_their_keys() {
+        let positions = YamlPositions::load("outside.yaml", SOURCE);
+        assert_eq!(
+            positions.at(&["outside_types", "users"]).to_string(),
+            "outside.yaml line 2 column 3 (outside_types.users)"
+        );
+        assert_eq!(
+            positions
+                .at(&["outside_types", "users", "tags"])
+                .to_string(),
+            "outside.yaml line 4 column 5 (outside_types.users.tags)"
+        );
+        assert_eq!(
+            positions.at(&["types", "note"]).to_string(),
+            "outside.yaml line 8 column 3 (types.note)"
+        );
+    }
+
+    #[test]
+    fn names_the_path_of_missing_values() {
+        let positions = YamlPositions::load("outside.yaml", SOURCE);
+        assert_eq!(
+            positions.at(&["outside_types", "posts"]).to_string(),
+            "outside.yaml (outside_types.posts)"
+        );
+    }
+}
@@ -1,2 +1,7 @@
+pub mod constructors;
+pub mod enum_tables;
 pub mod generate_enum;
+pub mod postgres_domains;
 pub mod postgres_types;
+pub mod shared_enums;
+pub mod template;
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use stringcase::{pascal_case, snake_case};
+
+use crate::{config::workspace::ConstructorCase, enums::generate_enum::first_upper};
+
+/// Names the constructors of an enum's labels, in the same order.
+/// Characters that can't be in a purescript identifier are replaced with underscores,
+/// and constructors that wouldn't start with an upper case letter are prefixed.
+/// Panics if labels would share a constructor, listing every collision.
+pub fn constructor_names(
+    enum_name: &str,
+    labels: &[String],
+    case: ConstructorCase,
+    prefix: &str,
+) -> Vec<String> {
+    let names: Vec<String> = labels
+        .iter()
+        .map(|label| constructor_name(label, case, prefix))
+        .collect();
+
+    let mut by_name: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, label) in names.iter().zip(labels) {
+        by_name.entry(name).or_default().push(label);
+    }
+    let collisions: Vec<String> = by_name
+        .iter()
+        .filter(|(_, labels)| labels.len() > 1)
+        .map(|(name, labels)| format!("  {name}: '{}'", labels.join("', '")))
+        .collect();
+    if !collisions.is_empty() {
+        panic!(
+            "Enum {enum_name} has labels that would generate the same constructor:\n{}\nSet enum_constructors in the spago workspace config yaml to name them differently.",
+            collisions.join("\n")
+        );
+    }
+    names
+}
+
+fn constructor_name(label: &str, case: ConstructorCase, prefix: &str) -> String {
+    let name = match case {
+        ConstructorCase::FirstUpper => first_upper(label),
+        ConstructorCase::Pascal => pascal_case(label),
+        ConstructorCase::ScreamingSnake => snake_case(label).to_uppercase(),
+    };
+    let name: String = name
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '_' || c == '\'' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    match name.starts_with(char::is_uppercase) {
+        true => name,
+        false => format!("{prefix}{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn names_constructors_in_each_case() {
+        let labels = labels(&["in_progress", "done"]);
+        assert_eq!(
+            constructor_names("status", &labels, ConstructorCase::FirstUpper, "V"),
+            ["In_progress", "Done"]
+        );
+        assert_eq!(
+            constructor_names("status", &labels, ConstructorCase::Pascal, "V"),
+            ["InProgress", "Done"]
+        );
+        assert_eq!(
+            constructor_names("status", &labels, ConstructorCase::ScreamingSnake, "V"),
+            ["IN_PROGRESS", "DONE"]
+        );
+    }
+
+    #[test]
+    fn replaces_invalid_characters_and_prefixes_non_upper_case_names() {
+        assert_eq!(
+            constructor_names(
+                "size",
+                &labels(&["x.large", "2xl", "_hidden"]),
+                ConstructorCase::FirstUpper,
+                "V"
+            ),
+            ["X_large", "V2xl", "V_hidden"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Sign_in: 'sign-in', 'sign in'")]
+    fn panics_listing_every_collision() {
+        constructor_names(
+            "event",
+            &labels(&["sign-in", "sign in", "sign_out"]),
+            ConstructorCase::FirstUpper,
+            "V",
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use sqlx::{PgPool, Result};
+use stringcase::pascal_case;
+
+use crate::{
+    config::workspace::{EnumTable, WorkspaceConfig},
+    enums::{
+        constructors::constructor_names, generate_enum::write_shared_enum,
+        postgres_types::PursTypes, shared_enums::DataEnum,
+    },
+};
+
+#[derive(sqlx::FromRow, Debug)]
+struct EnumTableRow {
+    value: String,
+    comment: Option<String>,
+}
+
+/// Generates a shared enum for each configured Hasura enum table from the rows in the database,
+/// documenting each constructor with its comment. They are keyed by their GraphQL enum name
+/// so every role uses them, whether or not it can see the table.
+/// Returns the generated types along with each enum, so variants can be converted to them.
+pub async fn fetch_enum_tables(
+    pool: &PgPool,
+    workspace_config: &WorkspaceConfig,
+) -> Result<(PursTypes, Vec<DataEnum>)> {
+    let mut purs_types = HashMap::new();
+    let mut data_enums = vec![];
+    for enum_table in workspace_config.enum_tables.iter() {
+        let EnumTable {
+            schema,
+            table,
+            graphql_name,
+            value_column,
+            comment_column,
+        } = enum_table;
+
+        // Enum tables don't need a comment column
+        let has_comments: bool = sqlx::query_scalar(
+            r#"SELECT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = $1 AND table_name = $2 AND column_name = $3
+      )"#,
+        )
+        .bind(schema)
+        .bind(table)
+        .bind(comment_column)
+        .fetch_one(pool)
+        .await?;
+        let comment = match has_comments {
+            true => format!("{}::text", quote_ident(comment_column)),
+            false => "NULL::text".to_string(),
+        };
+
+        let rows: Vec<EnumTableRow> = sqlx::query_as::<_, EnumTableRow>(&format!(
+            "SELECT {}::text AS value, {comment} AS comment FROM {}.{} ORDER BY 1;",
+            quote_ident(value_column),
+            quote_ident(schema),
+            quote_ident(table),
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        let name = pascal_case(graphql_name);
+        let constructors = &workspace_config.enum_constructors;
+        let original_values: Vec<String> = rows.iter().map(|row| row.value.clone()).collect();
+        // Hasura represents empty enums with a placeholder value
+        let (values, original_values, docs) = match rows.is_empty() {
+            true => (
+                vec!["ENUM_PLACEHOLDER".to_string()],
+                vec!["_PLACEHOLDER".to_string()],
+                vec![None],
+            ),
+            false => (
+                constructor_names(
+                    &name,
+                    &original_values,
+                    constructors.graphql,
+                    &constructors.prefix,
+                ),
+                original_values,
+                rows.iter().map(|row| row.comment.clone()).collect(),
+            ),
+        };
+
+        let (package, module, type_) =
+            write_shared_enum(&name, &values, &original_values, &docs, workspace_config);
+        purs_types.insert(
+            graphql_name.clone(),
+            (package.clone(), module.clone(), type_.clone()),
+        );
+        if !rows.is_empty() {
+            data_enums.push(DataEnum {
+                package,
+                module,
+                name: type_,
+                values: original_values,
+                constructors: values,
+            });
+        }
+    }
+    Ok((purs_types, data_enums))
+}
+
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
@@ -2,71 +2,188 @@ use cynic_introspection::EnumType;
 use stringcase::pascal_case;
 
 use crate::config::workspace::WorkspaceConfig;
+use crate::enums::shared_enums::{DataEnum, SharedEnums};
+use crate::enums::template::enum_context;
 use crate::purescript_gen::purescript_enum::Enum;
 use crate::purescript_gen::purescript_import::PurescriptImport;
 use crate::purescript_gen::purescript_variant::Variant;
 use crate::write::write;
 
+/// Hasura's enums of each table's column and constraint names
+const COLUMN_ENUM_SUFFIXES: [&str; 3] = ["_select_column", "_update_column", "_constraint"];
+
+/// Whether an enum names a table's columns or constraints,
+/// which are generated as variants with a row of their values
+pub fn is_column_enum(name: &str) -> bool {
+    COLUMN_ENUM_SUFFIXES
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
 pub async fn generate_enum(
     en: &EnumType,
+    shared_enums: &SharedEnums,
     imports: &mut Vec<PurescriptImport>,
-    workspace_config: &WorkspaceConfig,
 ) -> Option<Variant> {
-    // TODO this env could be faster if it was pulled in and parsed once at the start
-    // TODO check timings to see if it makes a difference
-    // Fetch the global enum suffixes
-    let global_enum_suffixes_env =
-        std::env::var("SHARED_ENUM_SUFFIXES").expect("SHARED_ENUM_SUFFIXES must be set");
-    let global_enum_suffixes: Vec<&str> = global_enum_suffixes_env.split(",").collect();
-
-    // Empty enums in Hasura are represented as a single value with the name "_PLACEHOLDER"
-    // purescript enums cannot start with an underscore, so we need to replace it with a different placeholder
-    let values = if en
-        .values
-        .iter()
-        .next()
-        .expect("Enums should have at least one value.")
-        .name
-        == "_PLACEHOLDER"
-    {
-        vec!["ENUM_PLACEHOLDER".to_string()]
-    } else {
-        en.values.iter().map(|v| first_upper(&v.name)).collect()
-    };
     let original_values: Vec<String> = en.values.iter().map(|v| v.name.clone()).collect();
     let name: String = pascal_case(&en.name);
 
-    // Some enums are shared between all schemas
-    if global_enum_suffixes
-        .iter()
-        .any(|suffix| name.ends_with(suffix))
-    {
-        let e = Enum::new(&name).with_values(&values).to_string();
+    // Some enums are shared between all schemas, and are written once for every role
+    if let Some(shared) = shared_enums.get(&en.name) {
+        imports.push(PurescriptImport::new(&shared.module, &shared.package).add_specified(&name));
+        return None;
+    }
 
-        let instances = enum_instances(&name, &values, &original_values);
-        let package_name = pascal_case(&workspace_config.shared_graphql_enums_lib);
-        let module_name = format!("{package_name}.{name}");
-        imports.push(PurescriptImport::new(&module_name, "oa-gql-enums").add_specified(&name));
+    // Otherwise write schema-specific variant enums
+    let variant = Variant::new(&name).with_values(&original_values);
+    // Column and constraint names are given as a row, so they can be checked per table.
+    // They name a table's columns rather than values, so aren't converted to enum data types.
+    if is_column_enum(&en.name) {
+        return Some(variant.with_row());
+    }
 
-        let lib_path = format!(
-            "{}{}",
-            &workspace_config.shared_graphql_enums_dir, &workspace_config.shared_graphql_enums_lib
-        );
-        write(
-            &format!("{lib_path}/src/{package_name}/{name}.purs"),
-            &format!(
-                "module {module_name} ({name}(..)) where\n\n{MODULE_IMPORTS}\n\n{e}{instances}"
-            ),
+    Some(with_conversions(
+        variant,
+        &original_values,
+        shared_enums.supersets(&original_values),
+        imports,
+    ))
+}
+
+/// Adds conversions between a variant and each enum data type with all of its values.
+/// The data types are imported qualified by their module, so their names and constructors
+/// can't clash with each other or the schema's other imports.
+/// Conversions are named after the data type, or `Data` when it has the variant's name,
+/// e.g. `orderByToSortOrder` and `orderByToData`.
+fn with_conversions(
+    mut variant: Variant,
+    original_values: &[String],
+    data_enums: Vec<&DataEnum>,
+    imports: &mut Vec<PurescriptImport>,
+) -> Variant {
+    // The name of each conversion along with the data type it converts
+    let mut converted: Vec<(&str, String)> = vec![];
+    for data_enum in data_enums {
+        let module = &data_enum.module;
+        let qualified_type = format!("{module}.{}", data_enum.name);
+        let conversion_name = match data_enum.name == variant.name() {
+            true => "Data",
+            false => &data_enum.name,
+        };
+        // Types with the same name would give conversions with the same name
+        if let Some((_, first)) = converted.iter().find(|(name, _)| *name == conversion_name) {
+            println!(
+                "Skipping conversions between {} and {qualified_type}, which would have the same names as those for {first}",
+                variant.name()
+            );
+            continue;
+        }
+        imports.push(PurescriptImport::new(module, &data_enum.package).with_as_name(module));
+        let constructors = original_values
+            .iter()
+            .map(|value| {
+                let i = data_enum
+                    .values
+                    .iter()
+                    .position(|v| v == value)
+                    .expect("Enum data types to convert to should have every value.");
+                (
+                    value.clone(),
+                    format!("{module}.{}", data_enum.constructors[i]),
+                )
+            })
+            .collect();
+        variant = variant.with_conversion(
+            conversion_name,
+            &qualified_type,
+            constructors,
+            original_values.len() == data_enum.values.len(),
         );
-        write(&format!("{lib_path}/spago.yaml"), &enums_spago_yaml());
-        None
-    // Otherwise write schema-specific variant enums
-    } else {
-        Some(Variant::new(&name).with_values(&original_values))
+        converted.push((conversion_name, qualified_type));
     }
+    variant
+}
+
+/// The `(package, module)` of an enum shared by every schema
+pub fn shared_enum_module(name: &str, workspace_config: &WorkspaceConfig) -> (String, String) {
+    let package = &workspace_config.shared_graphql_enums_lib;
+    (package.clone(), format!("{}.{name}", pascal_case(package)))
+}
+
+/// Writes an enum shared by every schema to the shared GraphQL enums package,
+/// returning its `(package, import, type)`.
+/// The package's spago.yaml is written once for every enum by `write_shared_enums_spago_yaml`.
+pub fn write_shared_enum(
+    name: &str,
+    values: &Vec<String>,
+    original_values: &[String],
+    docs: &[Option<String>],
+    workspace_config: &WorkspaceConfig,
+) -> (String, String, String) {
+    let (package, module_name) = shared_enum_module(name, workspace_config);
+    let package_name = pascal_case(&package);
+
+    let instances: Vec<_> = workspace_config
+        .enum_instances
+        .iter()
+        .filter_map(|instance| Some((instance, instance.graphql.as_ref()?)))
+        .collect();
+
+    let data = Enum::new(name)
+        .with_values(values)
+        .with_docs(docs)
+        .to_string();
+    let context = enum_context(
+        &module_name,
+        name,
+        &data,
+        values,
+        original_values,
+        &instances,
+    );
+
+    write(
+        &format!(
+            "{}/src/{package_name}/{name}.purs",
+            shared_enums_path(workspace_config)
+        ),
+        &workspace_config.enum_templates.graphql.render(&context),
+    );
+    (package, module_name, name.to_string())
 }
 
-fn first_upper(s: &str) -> String {
+/// Writes the shared GraphQL enums package's spago.yaml, with the template dependencies
+/// and the package of every instance generated for GraphQL enums
+pub fn write_shared_enums_spago_yaml(workspace_config: &WorkspaceConfig) {
+    let mut dependencies: Vec<&str> = workspace_config
+        .enum_templates
+        .dependencies
+        .iter()
+        .map(String::as_str)
+        .chain(
+            workspace_config
+                .enum_instances
+                .iter()
+                .filter(|instance| instance.graphql.is_some())
+                .map(|instance| instance.package.as_str()),
+        )
+        .collect();
+    dependencies.sort();
+    dependencies.dedup();
+    write(
+        &format!("{}/spago.yaml", shared_enums_path(workspace_config)),
+        &enums_spago_yaml(&workspace_config.shared_graphql_enums_lib, &dependencies),
+    );
+}
+
+fn shared_enums_path(workspace_config: &WorkspaceConfig) -> String {
+    format!(
+        "{}{}",
+        &workspace_config.shared_graphql_enums_dir, &workspace_config.shared_graphql_enums_lib
+    )
+}
+
+pub fn first_upper(s: &str) -> String {
     let mut c = s.chars();
     match c.next() {
         None => String::new(),
@@ -74,120 +191,14 @@ fn first_upper(s: &str) -> String {
     }
 }
 
-fn enum_instances(name: &str, values: &Vec<String>, original_values: &Vec<String>) -> String {
-    let mut instances = String::new();
-    instances.push_str(&format!(
-        "\n\ninstance MakeFixture {name} where mkFixture = {}",
-        values[0]
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance FC.Decode {name} where\n  decode = unsafeFromForeign >>> decodeJson >>> lmap (D.printJsonDecodeError >>> F.ForeignError >>> pure) >>> except",
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance FC.Encode {name} where\n  encode = encodeJson >>> unsafeToForeign"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance Eq {name} where\n  eq = eq `on` show"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance Ord {name} where\n  compare = compare `on` show"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance GqlArgString {name} where\n  toGqlArgStringImpl = show"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance DecodeJson {name} where\n  decodeJson = decodeJson >=> case _ of\n    {}\n    s -> Left $ TypeMismatch $ \"Not a {name}: \" <> s",
-        values.iter().zip(original_values.iter())
-            .map(|(v, ov)| format!("\"{ov}\" -> pure {v}")).collect::<Vec<String>>().join("\n    ")
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance EncodeJson {name} where\n  encodeJson = show >>> encodeJson"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance DecodeHasura {name} where\n  decodeHasura = decodeJson"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance EncodeHasura {name} where\n  encodeHasura = encodeJson"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance DecodeOa {name} where
-    decodeOa = FC.decode"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance EncodeOa {name} where
-    encodeOa = FC.encode"
-    ));
-    instances.push_str(&format!(
-        "\n\ninstance Show {name} where\n  show a = case a of\n    {}",
-        values
-            .iter()
-            .zip(original_values.iter())
-            .map(|(v, ov)| format!("{} -> \"{}\"", v, ov))
-            .collect::<Vec<String>>()
-            .join("\n    ")
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Enum {name} where\n  succ a = case a of\n    {}\n  pred a = case a of\n    {}",
-        values
-            .iter()
-            .enumerate()
-            .map(|(i, v)| {
-                if i == values.len() - 1 {
-                    format!("{} -> Nothing", v)
-                } else {
-                    format!("{} -> Just {}", v, values[i + 1])
-                }
-            })
-            .collect::<Vec<String>>()
-            .join("\n    "),
-        values
-            .iter()
-            .enumerate()
-            .map(|(i, v)| {
-                if i == 0 {
-                    format!("{} -> Nothing", v)
-                } else {
-                    format!("{} -> Just {}", v, values[i - 1])
-                }
-            })
-            .collect::<Vec<String>>()
-            .join("\n    ")
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Bounded {name} where\n  top = {}\n  bottom = {}",
-        values
-            .last()
-            .expect("Enums should have at least one value in order to get last."),
-        values
-            .first()
-            .expect("Enums should have at least one value in order to get first.")
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance BoundedEnum {name} where\n  cardinality = Cardinality {}\n  toEnum a = case a of\n    {}\n    _ -> Nothing\n  fromEnum a = case a of\n    {}",
-        values.len(),
-        values
-            .iter()
-            .enumerate()
-            .map(|(i, v)| format!("{} -> Just {}", i, v))
-            .collect::<Vec<String>>()
-            .join("\n    "),
-        values
-            .iter()
-            .enumerate()
-            .map(|(i, v)| format!("{} -> {}", v, i))
-            .collect::<Vec<String>>()
-            .join("\n    ")
-    ));
-
-    instances
-}
-
-fn enums_spago_yaml() -> String {
-    r#"package:
-  name: oa-gql-enums
+fn enums_spago_yaml(name: &str, dependencies: &[&str]) -> String {
+    let dependencies: String = dependencies
+        .iter()
+        .map(|dependency| format!("    - {dependency}\n"))
+        .collect();
+    format!(
+        r#"package:
+  name: {name}
   dependencies:
     - argonaut
     - argonaut-codecs
@@ -201,29 +212,67 @@ fn enums_spago_yaml() -> String {
     - prelude
     - simple-json
     - transformers
-    - oa-make-fixture
-    - oa-encode-decode
-"#
-    .to_string()
+{dependencies}"#
+    )
 }
 
-const MODULE_IMPORTS: &str = r#"import Prelude
-
-import Data.Argonaut.Decode (class DecodeJson, JsonDecodeError(..), decodeJson)
-import Data.Argonaut.Encode (class EncodeJson, encodeJson)
-import Data.Enum (class Enum, class BoundedEnum, Cardinality(..))
-import Data.Either (Either(..))
-import Data.Function (on)
-import Data.Maybe (Maybe(..))
-import GraphQL.Client.ToGqlString (class GqlArgString)
-import GraphQL.Hasura.Decode (class DecodeHasura)
-import GraphQL.Hasura.Encode (class EncodeHasura)
-import OaMakeFixture (class MakeFixture)
-import Foreign (unsafeFromForeign, unsafeToForeign)
-import Foreign as F
-import Data.Argonaut.Decode as D
-import Control.Monad.Except (except)
-import Data.Bifunctor (lmap)
-import Foreign.Class as FC
-import Class.EncodeOa (class EncodeOa)
-import Class.DecodeOa (class DecodeOa)"#;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_enum(module: &str, name: &str, values: &[&str]) -> DataEnum {
+        DataEnum {
+            package: "enums".to_string(),
+            module: module.to_string(),
+            name: name.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+            constructors: values.iter().map(|value| pascal_case(value)).collect(),
+        }
+    }
+
+    fn conversions(data_enums: &[DataEnum]) -> (String, Vec<PurescriptImport>) {
+        let values = vec!["asc".to_string(), "desc".to_string()];
+        let mut imports = vec![];
+        let variant = with_conversions(
+            Variant::new("OrderBy").with_values(&values),
+            &values,
+            data_enums.iter().collect(),
+            &mut imports,
+        );
+        (variant.helpers_to_string(), imports)
+    }
+
+    #[test]
+    fn converts_to_data_types_named_after_them() {
+        let (helpers, imports) = conversions(&[data_enum(
+            "Enums.SortOrder",
+            "SortOrder",
+            &["asc", "desc", "random"],
+        )]);
+        assert!(helpers.contains(
+            "orderByToSortOrder :: OrderBy -> Enums.SortOrder.SortOrder\norderByToSortOrder = case_\n  # on (Proxy :: Proxy \"asc\") (\\_ -> Enums.SortOrder.Asc)"
+        ));
+        assert!(
+            helpers.contains("orderByFromSortOrder :: Enums.SortOrder.SortOrder -> Maybe OrderBy")
+        );
+        assert_eq!(imports.len(), 1);
+    }
+
+    #[test]
+    fn converts_to_data_types_with_the_variants_name() {
+        let (helpers, _) = conversions(&[data_enum("Enums.OrderBy", "OrderBy", &["asc", "desc"])]);
+        assert!(helpers.contains("orderByToData :: OrderBy -> Enums.OrderBy.OrderBy"));
+        assert!(helpers.contains("orderByFromData :: Enums.OrderBy.OrderBy -> OrderBy"));
+    }
+
+    #[test]
+    fn skips_data_types_whose_conversions_would_share_a_name() {
+        let (helpers, imports) = conversions(&[
+            data_enum("Enums.SortOrder", "SortOrder", &["asc", "desc"]),
+            data_enum("Postgres.SortOrder", "SortOrder", &["asc", "desc"]),
+        ]);
+        assert_eq!(helpers.matches("orderByToSortOrder ::").count(), 1);
+        assert!(!helpers.contains("Postgres.SortOrder"));
+        assert_eq!(imports.len(), 1);
+    }
+}
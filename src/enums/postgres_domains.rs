@@ -0,0 +1,378 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use sqlx::{PgPool, Result};
+
+use crate::{
+    config::workspace::PostgresSchemas,
+    enums::postgres_types::{PostgresNames, PursTypes, SCHEMA_FILTER},
+    hasura_types::{scalar_has_instance, scalar_type},
+    purescript_gen::{
+        purescript_argument::Argument,
+        purescript_import::PurescriptImport,
+        purescript_instance::{derive_new_type_instance, derive_newtype_instance},
+        purescript_record::{Field, PurescriptRecord},
+        purescript_type::PurescriptType,
+    },
+    write::write,
+};
+
+/// A Postgres domain, e.g. `email_address` over `citext`
+#[derive(sqlx::FromRow, Debug)]
+pub struct DomainType {
+    pub schema: String,
+    pub name: String,
+    base_schema: String,
+    base_type: String,
+}
+
+/// A standalone Postgres composite type, rather than the row type of a table
+#[derive(sqlx::FromRow, Debug)]
+pub struct CompositeType {
+    pub schema: String,
+    pub name: String,
+    attribute_names: Vec<String>,
+    attribute_schemas: Vec<String>,
+    attribute_types: Vec<String>,
+}
+
+const DOMAIN_CLASSES: [&str; 6] = [
+    "Eq",
+    "Ord",
+    "Show",
+    "GqlArgString",
+    "DecodeHasura",
+    "EncodeHasura",
+];
+
+const COMPOSITE_CLASSES: [&str; 3] = ["Eq", "DecodeHasura", "EncodeHasura"];
+
+pub async fn fetch_domains(pool: &PgPool, schemas: &PostgresSchemas) -> Result<Vec<DomainType>> {
+    sqlx::query_as::<_, DomainType>(&format!(
+        r#"SELECT pg_namespace.nspname::text AS schema, pg_type.typname::text AS name,
+        base_namespace.nspname::text AS base_schema, base.typname::text AS base_type
+      FROM pg_type
+      INNER JOIN pg_namespace ON pg_namespace.oid = pg_type.typnamespace
+      INNER JOIN pg_type base ON base.oid = pg_type.typbasetype
+      INNER JOIN pg_namespace base_namespace ON base_namespace.oid = base.typnamespace
+      WHERE pg_type.typtype = 'd' AND {SCHEMA_FILTER}
+      ORDER BY pg_namespace.nspname, pg_type.typname;"#,
+    ))
+    .bind(&schemas.include)
+    .bind(&schemas.exclude)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn fetch_composites(
+    pool: &PgPool,
+    schemas: &PostgresSchemas,
+) -> Result<Vec<CompositeType>> {
+    sqlx::query_as::<_, CompositeType>(&format!(
+        r#"SELECT pg_namespace.nspname::text AS schema, pg_type.typname::text AS name,
+        array_agg(pg_attribute.attname::text ORDER BY pg_attribute.attnum) AS attribute_names,
+        array_agg(attribute_namespace.nspname::text ORDER BY pg_attribute.attnum) AS attribute_schemas,
+        array_agg(attribute_type.typname::text ORDER BY pg_attribute.attnum) AS attribute_types
+      FROM pg_type
+      INNER JOIN pg_namespace ON pg_namespace.oid = pg_type.typnamespace
+      INNER JOIN pg_class ON pg_class.oid = pg_type.typrelid AND pg_class.relkind = 'c'
+      INNER JOIN pg_attribute ON pg_attribute.attrelid = pg_class.oid
+        AND pg_attribute.attnum > 0 AND NOT pg_attribute.attisdropped
+      INNER JOIN pg_type attribute_type ON attribute_type.oid = pg_attribute.atttypid
+      INNER JOIN pg_namespace attribute_namespace ON attribute_namespace.oid = attribute_type.typnamespace
+      WHERE pg_type.typtype = 'c' AND {SCHEMA_FILTER}
+      GROUP BY pg_namespace.nspname, pg_type.typname
+      ORDER BY pg_namespace.nspname, pg_type.typname;"#,
+    ))
+    .bind(&schemas.include)
+    .bind(&schemas.exclude)
+    .fetch_all(pool)
+    .await
+}
+
+/// Writes a newtype over its base type for each domain, adding them to `purs_types`.
+/// Returns the packages the modules depend on.
+pub fn write_domains(
+    domains: &[DomainType],
+    names: &PostgresNames,
+    purs_types: &mut PursTypes,
+    lib_path: &str,
+    package: &str,
+) -> BTreeSet<String> {
+    let mut dependencies = BTreeSet::new();
+    // The classes each written domain derives, so domains based on it derive the same
+    let mut written: HashMap<(&str, &str), Vec<&'static str>> = HashMap::new();
+
+    // Domains can be based on other domains, so write those bases first
+    let mut pending: Vec<&DomainType> = domains.iter().collect();
+    while !pending.is_empty() {
+        let waiting_on: HashSet<(&str, &str)> = pending
+            .iter()
+            .map(|domain| (domain.schema.as_str(), domain.name.as_str()))
+            .collect();
+        let (ready, waiting): (Vec<&DomainType>, Vec<&DomainType>) =
+            pending.into_iter().partition(|domain| {
+                !waiting_on.contains(&(domain.base_schema.as_str(), domain.base_type.as_str()))
+            });
+        if ready.is_empty() {
+            panic!("Postgres domains are based on each other in a cycle: {waiting:?}");
+        }
+
+        for domain in ready {
+            let (path, import, type_) = names.names(&domain.schema, &domain.name);
+            let mut imports = vec![];
+            let base = resolve_type(
+                &domain.base_schema,
+                &domain.base_type,
+                names,
+                purs_types,
+                package,
+                &mut imports,
+                &mut dependencies,
+            );
+            let classes = base_classes(
+                &domain.base_schema,
+                &domain.base_type,
+                names,
+                purs_types,
+                &written,
+            );
+            let mut newtype = PurescriptType::new(&type_, vec![], base);
+            newtype.set_newtype(true);
+
+            write(
+                &format!("{lib_path}/src/{path}.purs"),
+                &newtype_module(&import, &newtype, &classes, imports),
+            );
+            written.insert((&domain.schema, &domain.name), classes);
+            for key in names.keys(&domain.schema, &domain.name) {
+                purs_types.insert(key, (package.to_string(), import.clone(), type_.clone()));
+            }
+        }
+        pending = waiting;
+    }
+
+    dependencies.insert("newtype".to_string());
+    dependencies
+}
+
+/// Writes a newtype over a record for each composite type, adding them to `purs_types`.
+/// Postgres can't constrain the attributes of composite types, so every field is a `Maybe`.
+/// Returns the packages the modules depend on.
+pub fn write_composites(
+    composites: &[CompositeType],
+    names: &PostgresNames,
+    purs_types: &mut PursTypes,
+    lib_path: &str,
+    package: &str,
+) -> BTreeSet<String> {
+    let mut dependencies = BTreeSet::new();
+
+    // Composite types can have attributes of other composite types, so write those first
+    let mut pending: Vec<&CompositeType> = composites.iter().collect();
+    while !pending.is_empty() {
+        let waiting_on: HashSet<(&str, &str)> = pending
+            .iter()
+            .map(|composite| (composite.schema.as_str(), composite.name.as_str()))
+            .collect();
+        let (ready, waiting): (Vec<&CompositeType>, Vec<&CompositeType>) =
+            pending.into_iter().partition(|composite| {
+                !composite
+                    .attribute_schemas
+                    .iter()
+                    .zip(composite.attribute_types.iter())
+                    .any(|(schema, attribute_type)| {
+                        // Array attributes depend on their element type, e.g. `_address`
+                        let element = attribute_type.strip_prefix('_').unwrap_or(attribute_type);
+                        waiting_on.contains(&(schema.as_str(), element))
+                    })
+            });
+        if ready.is_empty() {
+            panic!(
+                "Postgres composite types have attributes of each other in a cycle: {waiting:?}"
+            );
+        }
+
+        for composite in ready {
+            write_composite(
+                composite,
+                names,
+                purs_types,
+                lib_path,
+                package,
+                &mut dependencies,
+            );
+        }
+        pending = waiting;
+    }
+
+    if !composites.is_empty() {
+        dependencies.extend(["maybe".to_string(), "newtype".to_string()]);
+    }
+    dependencies
+}
+
+/// Writes the newtype over a record for a composite type, adding it to `purs_types`
+fn write_composite(
+    composite: &CompositeType,
+    names: &PostgresNames,
+    purs_types: &mut PursTypes,
+    lib_path: &str,
+    package: &str,
+    dependencies: &mut BTreeSet<String>,
+) {
+    let (path, import, type_) = names.names(&composite.schema, &composite.name);
+    let mut imports = vec![PurescriptImport::new("Data.Maybe", "maybe").add_specified("Maybe")];
+
+    let mut record = PurescriptRecord::new(&type_);
+    for ((name, schema), attribute_type) in composite
+        .attribute_names
+        .iter()
+        .zip(composite.attribute_schemas.iter())
+        .zip(composite.attribute_types.iter())
+    {
+        let field_type = resolve_type(
+            schema,
+            attribute_type,
+            names,
+            purs_types,
+            package,
+            &mut imports,
+            dependencies,
+        );
+        record.add_field(
+            Field::new(name).with_type_arg(Argument::new_type("Maybe").with_argument(field_type)),
+        );
+    }
+    let mut newtype = PurescriptType::new(&type_, vec![], Argument::new_record(record));
+    newtype.set_newtype(true);
+
+    write(
+        &format!("{lib_path}/src/{path}.purs"),
+        &newtype_module(&import, &newtype, &COMPOSITE_CLASSES, imports),
+    );
+    for key in names.keys(&composite.schema, &composite.name) {
+        purs_types.insert(key, (package.to_string(), import.clone(), type_.clone()));
+    }
+}
+
+/// The classes of `DOMAIN_CLASSES` a domain's base type has, so the domain can derive them.
+/// Generated enums have every class and domains have the classes they derived.
+fn base_classes(
+    schema: &str,
+    type_name: &str,
+    names: &PostgresNames,
+    purs_types: &PursTypes,
+    domains: &HashMap<(&str, &str), Vec<&'static str>>,
+) -> Vec<&'static str> {
+    if let Some(classes) = domains.get(&(schema, type_name)) {
+        return classes.clone();
+    }
+    if names
+        .keys(schema, type_name)
+        .iter()
+        .any(|key| purs_types.contains_key(key))
+    {
+        return DOMAIN_CLASSES.to_vec();
+    }
+
+    // Arrays have the classes of their elements, apart from being used as GraphQL arguments
+    if let Some(element) = type_name.strip_prefix('_') {
+        return base_classes(schema, element, names, purs_types, domains)
+            .into_iter()
+            .filter(|class| *class != "GqlArgString")
+            .collect();
+    }
+
+    let (type_, _) = scalar_type(type_name);
+    DOMAIN_CLASSES
+        .into_iter()
+        .filter(|class| scalar_has_instance(&type_, class))
+        .collect()
+}
+
+/// Resolves the purescript type of a Postgres type, preferring types that have already been generated
+fn resolve_type(
+    schema: &str,
+    type_name: &str,
+    names: &PostgresNames,
+    purs_types: &PursTypes,
+    package: &str,
+    imports: &mut Vec<PurescriptImport>,
+    dependencies: &mut BTreeSet<String>,
+) -> Argument {
+    let generated = names
+        .keys(schema, type_name)
+        .iter()
+        .find_map(|key| purs_types.get(key));
+    if let Some((type_package, import, type_)) = generated {
+        imports.push(PurescriptImport::new(import, type_package).add_specified(type_));
+        if type_package != package {
+            dependencies.insert(type_package.clone());
+        }
+        return Argument::new_type(type_);
+    }
+
+    // Array types are named after their element type with an underscore prefix, e.g. `_text`
+    if let Some(element) = type_name.strip_prefix('_') {
+        return Argument::new_type("Array").with_argument(resolve_type(
+            schema,
+            element,
+            names,
+            purs_types,
+            package,
+            imports,
+            dependencies,
+        ));
+    }
+
+    let (type_, import) = scalar_type(type_name);
+    if let Some((module, type_package)) = import {
+        imports.push(PurescriptImport::new(module, type_package).add_specified(&type_));
+        dependencies.insert(type_package.to_string());
+    }
+    Argument::new_type(&type_)
+}
+
+fn newtype_module(
+    module: &str,
+    newtype: &PurescriptType,
+    classes: &[&str],
+    mut imports: Vec<PurescriptImport>,
+) -> String {
+    imports.extend([
+        PurescriptImport::new("Prelude", "prelude"),
+        PurescriptImport::new("Data.Newtype", "newtype").add_specified("class Newtype"),
+        PurescriptImport::new("GraphQL.Hasura.Decode", "graphql-client")
+            .add_specified("class DecodeHasura"),
+        PurescriptImport::new("GraphQL.Hasura.Encode", "graphql-client")
+            .add_specified("class EncodeHasura"),
+    ]);
+    if classes.contains(&"GqlArgString") {
+        imports.push(
+            PurescriptImport::new("GraphQL.Client.ToGqlString", "graphql-client")
+                .add_specified("class GqlArgString"),
+        );
+    }
+    let imports = PurescriptImport::merge(&imports)
+        .iter_mut()
+        .map(|import| import.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut instances = vec![derive_new_type_instance(&newtype.name)];
+    instances.extend(
+        classes
+            .iter()
+            .map(|class| derive_newtype_instance(&newtype.name, class)),
+    );
+    let instances = instances
+        .iter()
+        .map(|instance| instance.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        "module {module} where\n\n{imports}\n\n{}\n\n{instances}\n",
+        newtype.to_string()
+    )
+}
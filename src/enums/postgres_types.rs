@@ -1,241 +1,338 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use sqlx::{postgres::PgPoolOptions, Result};
-use stringcase::{pascal_case, snake_case};
+use sqlx::{PgPool, Result};
+use stringcase::pascal_case;
 
 use crate::{
-    config::workspace::WorkspaceConfig, purescript_gen::purescript_enum::Enum, write::write,
+    config::{
+        postgres_connection::PostgresConnection,
+        workspace::{EnumOrder, PostgresDatabase, WorkspaceConfig},
+    },
+    enums::{
+        constructors::constructor_names,
+        enum_tables::fetch_enum_tables,
+        postgres_domains::{fetch_composites, fetch_domains, write_composites, write_domains},
+        shared_enums::DataEnum,
+        template::enum_context,
+    },
+    purescript_gen::purescript_enum::Enum,
+    write::write,
 };
 
-pub async fn fetch_types(
-    workspace_config: &WorkspaceConfig,
-) -> Result<HashMap<String, (String, String, String)>> {
-    let db_env = std::env::var("DATABASE_URL");
+/// Generated types keyed by their postgres type name, as `(package, import, type)`
+pub type PursTypes = HashMap<String, (String, String, String)>;
+
+const PUBLIC_SCHEMA: &str = "public";
 
-    // when no postgres enums are included, skip the enum generation
-    if db_env.is_err() {
-        return Ok(HashMap::new());
+/// Generates the Postgres enums, domains, composite types and enum tables of every database,
+/// returning the generated types along with every enum, so variants can be converted to them
+pub async fn fetch_types(workspace_config: &WorkspaceConfig) -> Result<(PursTypes, Vec<DataEnum>)> {
+    let mut hash_map = HashMap::new();
+    let mut data_enums = vec![];
+    for database in workspace_config.postgres_databases.iter() {
+        // When a database's URL isn't set, there's no database to generate its types from
+        let Some(connection) = PostgresConnection::from_env(&database.url_env) else {
+            match &database.name {
+                None => println!(
+                    "{} is not set, so Postgres enums won't be generated",
+                    database.url_env
+                ),
+                Some(name) => println!(
+                    "{} is not set, so Postgres enums won't be generated for the {name} database",
+                    database.url_env
+                ),
+            }
+            if database.name.is_none() && !workspace_config.enum_tables.is_empty() {
+                println!(
+                    "{} is not set, so enum tables will be generated from the GraphQL schema",
+                    database.url_env
+                );
+            }
+            continue;
+        };
+        let pool = connection.connect().await?;
+
+        let (types, enums) = fetch_database_types(&pool, database, workspace_config).await?;
+        // Types with the same name in another database would replace each other
+        let mut duplicates: Vec<&String> = types
+            .keys()
+            .filter(|key| hash_map.contains_key(*key))
+            .collect();
+        if !duplicates.is_empty() {
+            duplicates.sort();
+            panic!(
+                "The {} database has types already generated from another database: {}. Set a type_prefix for it in postgres_databases.",
+                database.name.as_deref().unwrap_or("default"),
+                duplicates.iter().map(|key| key.as_str()).collect::<Vec<&str>>().join(", ")
+            );
+        }
+        hash_map.extend(types);
+        data_enums.extend(enums);
+
+        // Enum tables are only read from the default database
+        if database.name.is_none() {
+            let (enum_tables, enum_table_data) = fetch_enum_tables(&pool, workspace_config).await?;
+            hash_map.extend(enum_tables);
+            data_enums.extend(enum_table_data);
+        }
     }
-    let database_url =
-        db_env.expect("DATABASE_URL should not be required but for some reason is...");
-    let pool = PgPoolOptions::new()
-        .max_connections(1)
-        .connect(&database_url)
-        .await
-        .expect("Failed to create pool");
-
-    let res: Vec<EnumType> = sqlx::query_as::<_, EnumType>(
-        r#"SELECT pg_type.typname AS enumtype, array_agg(pg_enum.enumlabel ORDER BY lower(pg_enum.enumlabel) ASC) as enumlabel
+
+    Ok((hash_map, data_enums))
+}
+
+/// Generates the Postgres enums, domains and composite types of a database into its own package
+async fn fetch_database_types(
+    pool: &PgPool,
+    database: &PostgresDatabase,
+    workspace_config: &WorkspaceConfig,
+) -> Result<(PursTypes, Vec<DataEnum>)> {
+    let label_order = match workspace_config.postgres_enums_order {
+        EnumOrder::Declaration => "pg_enum.enumsortorder",
+        EnumOrder::Alphabetical => "lower(pg_enum.enumlabel)",
+    };
+    let schemas = &workspace_config.postgres_enums_schemas;
+    let res: Vec<EnumType> = sqlx::query_as::<_, EnumType>(&format!(
+        r#"SELECT pg_namespace.nspname AS enumschema, pg_type.typname AS enumtype, array_agg(pg_enum.enumlabel ORDER BY {label_order} ASC) as enumlabel,
+        obj_description(pg_type.oid, 'pg_type') AS enumcomment
       FROM pg_type
+      INNER JOIN pg_namespace ON pg_namespace.oid = pg_type.typnamespace
       INNER JOIN pg_enum ON pg_enum.enumtypid = pg_type.oid
-      GROUP BY typname;"#,
-    )
-    .fetch_all(&pool)
+      WHERE {SCHEMA_FILTER}
+      GROUP BY pg_namespace.nspname, pg_type.typname, pg_type.oid
+      ORDER BY pg_namespace.nspname, pg_type.typname;"#,
+    ))
+    .bind(&schemas.include)
+    .bind(&schemas.exclude)
+    .fetch_all(pool)
     .await?;
+    let domains = fetch_domains(pool, schemas).await?;
+    let composites = fetch_composites(pool, schemas).await?;
+
+    let package = &database.lib;
+    let names = PostgresNames::new(
+        &database.module_prefix,
+        &database.type_prefix,
+        res.iter()
+            .map(|enum_row| enum_row.enumtype.as_str())
+            .chain(domains.iter().map(|domain| domain.name.as_str()))
+            .chain(composites.iter().map(|composite| composite.name.as_str())),
+    );
 
     let mut hash_map = HashMap::new();
+    let lib_path = format!("{}{package}", &workspace_config.postgres_enums_dir);
+    let mut dependencies: BTreeSet<String> = workspace_config
+        .enum_instances
+        .iter()
+        .filter(|instance| instance.postgres.is_some())
+        .map(|instance| instance.package.clone())
+        .chain(workspace_config.enum_templates.dependencies.iter().cloned())
+        .collect();
 
-    let package_name = pascal_case(&workspace_config.postgres_enums_lib);
-    let lib_path = format!(
-        "{}{}",
-        &workspace_config.postgres_enums_dir, &workspace_config.postgres_enums_lib
-    );
-    let package = &workspace_config.postgres_enums_lib;
-
+    let mut data_enums = vec![];
     for enum_row in res.iter() {
-        let name = enum_row.enumtype.clone();
-        let type_ = pascal_case(&name);
-        let import = format!("{package_name}.{type_}");
-        let contents = write_enum_module(&enum_row, &package_name);
-
-        write(
-            &format!("{lib_path}/src/{package_name}/{type_}.purs"),
-            &contents,
+        let (path, import, type_) = names.names(&enum_row.enumschema, &enum_row.enumtype);
+        let original_values: Vec<String> = match enum_row.enumlabel.as_ref() {
+            Some(v) => v.clone(),
+            None => vec!["ENUM_PLACEHOLDER".to_string()],
+        };
+        let constructors = &workspace_config.enum_constructors;
+        let values = constructor_names(
+            &type_,
+            &original_values,
+            constructors.postgres,
+            &constructors.prefix,
+        );
+        let contents = write_enum_module(
+            enum_row,
+            &import,
+            &type_,
+            &values,
+            &original_values,
+            workspace_config,
         );
+        write(&format!("{lib_path}/src/{path}.purs"), &contents);
+        for key in names.keys(&enum_row.enumschema, &enum_row.enumtype) {
+            hash_map.insert(key, (package.clone(), import.clone(), type_.clone()));
+        }
+        if enum_row.enumlabel.is_some() {
+            data_enums.push(DataEnum {
+                package: package.clone(),
+                module: import,
+                name: type_,
+                values: original_values,
+                constructors: values,
+            });
+        }
+    }
+
+    dependencies.extend(write_domains(
+        &domains,
+        &names,
+        &mut hash_map,
+        &lib_path,
+        package,
+    ));
+    dependencies.extend(write_composites(
+        &composites,
+        &names,
+        &mut hash_map,
+        &lib_path,
+        package,
+    ));
+    if !hash_map.is_empty() {
         write(
             &format!("{lib_path}/spago.yaml"),
-            &enums_spago_yaml(package),
+            &enums_spago_yaml(package, &dependencies),
         );
-        hash_map.insert(name, (package.clone(), import, type_));
     }
 
-    Ok(hash_map)
+    Ok((hash_map, data_enums))
+}
+
+/// Filters types to the configured schemas, taking the schemas to include as `$1` and exclude as `$2`.
+/// Types created by extensions aren't part of the app's schema, so they are skipped.
+pub const SCHEMA_FILTER: &str = r#"($1::text[] IS NULL OR pg_namespace.nspname = ANY($1))
+        AND NOT pg_namespace.nspname = ANY($2)
+        AND NOT EXISTS (
+          SELECT 1 FROM pg_depend
+          WHERE pg_depend.classid = 'pg_type'::regclass
+            AND pg_depend.objid = pg_type.oid
+            AND pg_depend.deptype = 'e'
+        )"#;
+
+/// Names the modules and types generated for Postgres types.
+/// Types outside the public schema are namespaced by their schema, e.g. `OaEnumsPostgres.Audit.Status`,
+/// and their types are prefixed with it too if the name is used in another schema.
+pub struct PostgresNames<'a> {
+    module_prefix: String,
+    /// The prefix Hasura gives the database's types, which `PursTypes` keys include
+    type_prefix: String,
+    /// The number of schemas each type name is used in
+    schema_counts: HashMap<&'a str, usize>,
 }
 
-fn enums_spago_yaml(name: &str) -> String {
+impl<'a> PostgresNames<'a> {
+    pub fn new(
+        module_prefix: &str,
+        type_prefix: &str,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        let mut schema_counts = HashMap::new();
+        for name in names {
+            *schema_counts.entry(name).or_default() += 1;
+        }
+        Self {
+            module_prefix: module_prefix.to_string(),
+            type_prefix: type_prefix.to_string(),
+            schema_counts,
+        }
+    }
+
+    /// Returns the file path within `src`, module and type name for a Postgres type
+    pub fn names(&self, schema: &str, name: &str) -> (String, String, String) {
+        let is_public = schema == PUBLIC_SCHEMA;
+        // Module prefixes can have several segments, e.g. `Oa.Analytics`
+        let prefix = self.module_prefix.replace('.', "/");
+        let path = match is_public {
+            true => format!("{prefix}/{}", pascal_case(name)),
+            false => format!("{prefix}/{}/{}", pascal_case(schema), pascal_case(name)),
+        };
+        let type_ = match is_public || !self.is_shared(name) {
+            true => pascal_case(name),
+            false => pascal_case(&format!("{schema}_{name}")),
+        };
+        (path.clone(), path.replace('/', "."), type_)
+    }
+
+    /// The names a type is found by in `PursTypes`.
+    /// Types outside the public schema are also registered as `<schema>_<type>`,
+    /// for scalar names that include the schema
+    pub fn keys(&self, schema: &str, name: &str) -> Vec<String> {
+        let prefix = &self.type_prefix;
+        let mut keys = vec![];
+        if schema != PUBLIC_SCHEMA {
+            keys.push(format!("{prefix}{schema}_{name}"));
+        }
+        if schema == PUBLIC_SCHEMA || !self.is_shared(name) {
+            keys.push(format!("{prefix}{name}"));
+        }
+        keys
+    }
+
+    fn is_shared(&self, name: &str) -> bool {
+        self.schema_counts.get(name).is_some_and(|count| *count > 1)
+    }
+}
+
+/// The dependencies of every enum module
+const ENUM_DEPENDENCIES: [&str; 12] = [
+    "argonaut",
+    "argonaut-codecs",
+    "arrays",
+    "bifunctors",
+    "either",
+    "enums",
+    "foreign",
+    "foreign-generic",
+    "graphql-client",
+    "prelude",
+    "simple-json",
+    "transformers",
+];
+
+fn enums_spago_yaml(name: &str, dependencies: &BTreeSet<String>) -> String {
+    let dependencies: BTreeSet<&str> = ENUM_DEPENDENCIES
+        .into_iter()
+        .chain(dependencies.iter().map(String::as_str))
+        .collect();
+    let dependencies: String = dependencies
+        .iter()
+        .map(|dependency| format!("    - {dependency}\n"))
+        .collect();
     format!(
         r#"package:
   name: {name}
   dependencies:
-    - argonaut
-    - argonaut-codecs
-    - arrays
-    - bifunctors
-    - either
-    - enums
-    - foreign
-    - foreign-generic
-    - graphql-client
-    - prelude
-    - simple-json
-    - transformers
-    - oa-make-fixture
-    - oa-encode-decode
-"#
+{dependencies}"#
     )
 }
 
 #[derive(sqlx::Type, sqlx::FromRow, Debug)]
 struct EnumType {
+    enumschema: String,
     enumtype: String,
     enumlabel: Option<Vec<String>>,
+    /// The enum type's comment, which can also document its values
+    enumcomment: Option<String>,
 }
 
-fn write_enum_module(enum_row: &EnumType, package_name: &str) -> String {
-    let name = pascal_case(&enum_row.enumtype);
-    let original_values: Vec<String> = match enum_row.enumlabel.as_ref() {
-        Some(v) => v.clone(),
-        None => vec!["ENUM_PLACEHOLDER".to_string()],
-    };
-    let values = original_values
+fn write_enum_module(
+    enum_row: &EnumType,
+    module: &str,
+    name: &str,
+    values: &Vec<String>,
+    original_values: &[String],
+    workspace_config: &WorkspaceConfig,
+) -> String {
+    let instances: Vec<_> = workspace_config
+        .enum_instances
         .iter()
-        .map(|v| snake_case(v).to_uppercase())
+        .filter_map(|instance| Some((instance, instance.postgres.as_ref()?)))
         .collect();
-
-    let mod_name = format!("module {package_name}.{name} where");
-    let enum_definition = Enum::new(&name).with_values(&values).to_string();
-    let instances_and_fns = enum_body(&name, &values, &original_values);
-
-    format!("{mod_name}\n\n{MODULE_IMPORTS}\n\n{enum_definition}\n\n{instances_and_fns}")
-}
-
-const MODULE_IMPORTS: &str = r#"import Prelude
-
-import Control.Monad.Except (except)
-import Data.Argonaut (encodeJson, fromString, printJsonDecodeError)
-import Data.Argonaut.Decode (class DecodeJson, JsonDecodeError(..), decodeJson)
-import Data.Array (findIndex, (!!))
-import Data.Bifunctor (lmap)
-import Data.Either (Either(..))
-import Data.Function (on)
-import Data.Enum (class Enum)
-import Foreign (ForeignError(ForeignError), readString)
-import Foreign.Class (class Decode, class Encode, decode, encode)
-import GraphQL.Client.ToGqlString (class GqlArgString)
-import GraphQL.Hasura.Decode (class DecodeHasura)
-import GraphQL.Hasura.Encode (class EncodeHasura)
-import OaMakeFixture (class MakeFixture)
-import Simple.JSON (class ReadForeign, class WriteForeign)
-import Class.EncodeOa (class EncodeOa)
-import Class.DecodeOa (class DecodeOa)"#;
-
-pub fn enum_body(name: &str, values: &Vec<String>, original_values: &Vec<String>) -> String {
-    let mut instances = String::new();
-
-    let in_array: String = values
-        .iter()
-        .enumerate()
-        .map(|(index, v)| {
-            let prefix = if index == 0 { "  [" } else { "  ," };
-            format!("{} {}", prefix, v)
-        })
-        .collect::<Vec<String>>()
-        .join("\n");
-
-    instances.push_str(&format!(
-        "all{name} :: Array {name}
-all{name} =
-{in_array}
-  ]"
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Eq {name} where\n  eq = eq `on` show",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Ord {name} where\n  compare = compare `on` show",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Enum {name} where\n  pred a = do\n    idx <- findIndex (eq a) all{name}\n    all{name} !! (idx - 1)\n  succ a = do\n    idx <- findIndex (eq a) all{name}\n    all{name} !! (idx + 1)",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Bounded {name} where\n  top = {}\n  bottom = {}",
-        values
-            .last()
-            .expect("Postgres enum should have at least one value to get last."),
-        values
-            .first()
-            .expect("Postgres enum should have at least one value to get first.")
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Decode {name} where\n  decode =\n    readString\n      >=>\n        ( fromString\n            >>> decodeJson\n            >>> lmap (printJsonDecodeError >>> ForeignError >>> pure)\n            >>> except\n        )",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Encode {name} where\n  encode = show >>> encode",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance WriteForeign {name} where\n  writeImpl = encode",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance ReadForeign {name} where\n  readImpl = decode",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance DecodeHasura {name} where\n  decodeHasura = decodeJson",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance EncodeHasura {name} where\n  encodeHasura = show >>> encodeJson",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance DecodeOa {name} where
-    decodeOa = decode"
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance EncodeOa {name} where
-    encodeOa = encode"
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance Show {name} where\n  show = case _ of\n    {}",
-        values
-            .iter()
-            .zip(original_values.iter())
-            .map(|(v, o)| format!("{} -> \"{}\"", v, o))
-            .collect::<Vec<String>>()
-            .join("\n    ")
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance GqlArgString {name} where\n  toGqlArgStringImpl = show >>> show",
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance DecodeJson {name} where\n  decodeJson =\n    decodeJson\n      >=> case _ of\n        {}\n        str ->\n          Left\n            $ TypeMismatch\n            $ \"Failed to decode {name} from string: \"\n                <> str",
-        values
-            .iter()
-            .zip(original_values.iter())
-            .map(|(v, o)| format!("\"{}\" -> pure {}", o, v))
-            .collect::<Vec<String>>()
-            .join("\n        "),
-    ));
-
-    instances.push_str(&format!(
-        "\n\ninstance MakeFixture {} where\n  mkFixture = {}",
-        name,
-        values
-            .first()
-            .expect("Postgres enum should have at least one value to get first.")
-    ));
-
-    instances
+    let (doc, docs) = match (
+        &enum_row.enumcomment,
+        &workspace_config.postgres_enum_value_docs,
+    ) {
+        (None, _) => (None, vec![]),
+        (Some(comment), None) => (Some(comment.clone()), vec![]),
+        (Some(comment), Some(value_docs)) => value_docs.split(comment, original_values),
+    };
+    let data = Enum::new(name)
+        .with_values(values)
+        .with_doc(doc)
+        .with_docs(&docs)
+        .to_string();
+    let context = enum_context(module, name, &data, values, original_values, &instances);
+
+    workspace_config.enum_templates.postgres.render(&context)
 }
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use cynic_introspection::{Schema, Type};
+use stringcase::pascal_case;
+
+use crate::{
+    config::workspace::{EnumMismatch, WorkspaceConfig},
+    enums::{
+        constructors::constructor_names,
+        generate_enum::{shared_enum_module, write_shared_enum, write_shared_enums_spago_yaml},
+        postgres_types::PursTypes,
+    },
+};
+
+/// Hasura's only value for enums without any values
+const PLACEHOLDER: &str = "_PLACEHOLDER";
+
+/// An enum generated as a data type rather than a variant
+#[derive(Clone)]
+pub struct DataEnum {
+    pub package: String,
+    pub module: String,
+    pub name: String,
+    /// The values as they are encoded
+    pub values: Vec<String>,
+    /// The constructor of each value
+    pub constructors: Vec<String>,
+}
+
+/// The GraphQL enums shared by every role, collected from every role's schema before any are generated
+/// so they can be checked against each other, along with every other enum generated as a data type
+pub struct SharedEnums {
+    /// The shared GraphQL enums by their GraphQL name
+    graphql: BTreeMap<String, DataEnum>,
+    /// Postgres enums and enum tables
+    database: Vec<DataEnum>,
+}
+
+impl SharedEnums {
+    /// Collects the shared GraphQL enums from each role's schema, merging their values.
+    /// When roles see different values for an enum, the differences are reported
+    /// and every value is generated, or generation fails if configured to.
+    pub fn new(
+        roles: &[String],
+        schemas: &[Schema],
+        postgres_types: &PursTypes,
+        database: Vec<DataEnum>,
+        workspace_config: &WorkspaceConfig,
+    ) -> Self {
+        let rules = &workspace_config.shared_graphql_enums;
+        let identical = match rules.identical {
+            true => identical_enums(schemas),
+            false => HashSet::new(),
+        };
+
+        // The values each role sees, by enum name
+        let mut enums: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+        for (role, schema) in roles.iter().zip(schemas) {
+            for type_ in schema.types.iter() {
+                let Type::Enum(en) = type_ else {
+                    continue;
+                };
+                // Internal Hasura enums and enums generated from the database aren't generated per role
+                if en.name.starts_with("__") || postgres_types.contains_key(&en.name) {
+                    continue;
+                }
+                if rules.is_shared(&en.name, identical.contains(&en.name)) {
+                    let values = en.values.iter().map(|v| v.name.as_str()).collect();
+                    enums.entry(&en.name).or_default().insert(role, values);
+                }
+            }
+        }
+
+        let mismatches: Vec<String> = enums
+            .iter()
+            .filter_map(|(name, role_values)| mismatch_report(name, role_values, roles))
+            .collect();
+        if !mismatches.is_empty() {
+            println!(
+                "Roles see different values for shared enums:\n{}",
+                mismatches.join("\n")
+            );
+            if let EnumMismatch::Fail = workspace_config.shared_graphql_enums_mismatch {
+                eprintln!(
+                    "Found {} shared enums that differ between roles. Make the roles see the same values or set shared_graphql_enums_mismatch to merge.",
+                    mismatches.len()
+                );
+                std::process::exit(1);
+            }
+        }
+
+        let graphql = enums
+            .iter()
+            .map(|(graphql_name, role_values)| {
+                let name = pascal_case(graphql_name);
+                let (package, module) = shared_enum_module(&name, workspace_config);
+                let (values, constructors) =
+                    merge_values(&name, role_values, roles, workspace_config);
+                let data_enum = DataEnum {
+                    package,
+                    module,
+                    name,
+                    values,
+                    constructors,
+                };
+                (graphql_name.to_string(), data_enum)
+            })
+            .collect();
+        Self { graphql, database }
+    }
+
+    /// The shared enum generated for a GraphQL enum, if it is shared
+    pub fn get(&self, graphql_name: &str) -> Option<&DataEnum> {
+        self.graphql.get(graphql_name)
+    }
+
+    /// Every enum generated as a data type that has all the given values
+    pub fn supersets(&self, values: &[String]) -> Vec<&DataEnum> {
+        self.graphql
+            .values()
+            .chain(self.database.iter())
+            .filter(|data_enum| {
+                data_enum.values.iter().all(|value| value != PLACEHOLDER)
+                    && values.iter().all(|value| data_enum.values.contains(value))
+            })
+            .collect()
+    }
+
+    /// Writes each shared GraphQL enum to the shared GraphQL enums package,
+    /// then the package's spago.yaml, which also covers the enum tables written there
+    pub fn write(&self, workspace_config: &WorkspaceConfig) {
+        for data_enum in self.graphql.values() {
+            write_shared_enum(
+                &data_enum.name,
+                &data_enum.constructors,
+                &data_enum.values,
+                &[],
+                workspace_config,
+            );
+        }
+        write_shared_enums_spago_yaml(workspace_config);
+    }
+}
+
+/// Finds the GraphQL enums that every role's schema has, with the same values
+fn identical_enums(schemas: &[Schema]) -> HashSet<String> {
+    let mut enums: HashMap<&str, (Vec<&str>, usize)> = HashMap::new();
+    for schema in schemas.iter() {
+        for type_ in schema.types.iter() {
+            let Type::Enum(en) = type_ else {
+                continue;
+            };
+            let values: Vec<&str> = en.values.iter().map(|v| v.name.as_str()).collect();
+            let (first_values, count) = enums.entry(&en.name).or_insert((values.clone(), 0));
+            if *first_values == values {
+                *count += 1;
+            }
+        }
+    }
+    enums
+        .into_iter()
+        .filter(|(_, (_, count))| *count == schemas.len())
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Merges the values every role sees for an enum, returning the values and their constructors.
+/// Values are merged in the order of the roles, with new values after the values already seen.
+fn merge_values(
+    name: &str,
+    role_values: &BTreeMap<&str, Vec<&str>>,
+    roles: &[String],
+    workspace_config: &WorkspaceConfig,
+) -> (Vec<String>, Vec<String>) {
+    let mut values: Vec<String> = vec![];
+    for role in roles.iter() {
+        for value in role_values.get(role.as_str()).into_iter().flatten() {
+            if *value != PLACEHOLDER && !values.iter().any(|v| v == value) {
+                values.push(value.to_string());
+            }
+        }
+    }
+
+    // Purescript enums cannot start with an underscore, so the placeholder needs a different constructor
+    if values.is_empty() {
+        return (
+            vec![PLACEHOLDER.to_string()],
+            vec!["ENUM_PLACEHOLDER".to_string()],
+        );
+    }
+    let constructors = &workspace_config.enum_constructors;
+    let constructors = constructor_names(name, &values, constructors.graphql, &constructors.prefix);
+    (values, constructors)
+}
+
+/// Lists the values each role sees for an enum, if they aren't all the same.
+/// Roles that see the same values are listed together.
+fn mismatch_report(
+    name: &str,
+    role_values: &BTreeMap<&str, Vec<&str>>,
+    roles: &[String],
+) -> Option<String> {
+    let mut by_values: BTreeMap<&Vec<&str>, Vec<&str>> = BTreeMap::new();
+    for role in roles.iter() {
+        if let Some(values) = role_values.get(role.as_str()) {
+            by_values.entry(values).or_default().push(role);
+        }
+    }
+    if by_values.len() < 2 {
+        return None;
+    }
+    let report = by_values
+        .iter()
+        .map(|(values, roles)| format!("    {}: {}", roles.join(", "), values.join(", ")))
+        .collect::<Vec<String>>()
+        .join("\n");
+    Some(format!("  {}\n{report}", pascal_case(name)))
+}
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use crate::config::workspace::EnumInstance;
+
+pub const GRAPHQL_ENUM_TEMPLATE: &str = include_str!("templates/graphql_enum.purs.template");
+pub const POSTGRES_ENUM_TEMPLATE: &str = include_str!("templates/postgres_enum.purs.template");
+
+/// A small mustache-like template for generated enum modules.
+/// `{{name}}` inserts a value, `{{#values}}...{{/values}}` repeats for each item of a list
+/// or renders when a flag is true, and `{{^is_last}}...{{/is_last}}` renders when a flag is false
+/// or a list is empty. Lines holding only a section tag are removed, so sections can sit on their own lines.
+#[derive(Debug, Clone)]
+pub struct Template {
+    file: String,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Value(String),
+    Section {
+        name: String,
+        inverted: bool,
+        nodes: Vec<Node>,
+    },
+}
+
+pub enum Value {
+    Text(String),
+    Flag(bool),
+    List(Vec<Context>),
+}
+
+pub type Context = HashMap<&'static str, Value>;
+
+impl Template {
+    pub fn parse(file: &str, source: &str) -> Self {
+        // The sections that are still open, along with the nodes of their parents
+        let mut open: Vec<(String, bool, Vec<Node>)> = vec![];
+        let mut nodes = vec![];
+        let mut pos = 0;
+
+        while let Some(start) = source[pos..].find("{{").map(|i| pos + i) {
+            let end = source[start..]
+                .find("}}")
+                .map(|i| start + i)
+                .unwrap_or_else(|| panic!("Unclosed {{{{ in enum template {file}"));
+            let tag = source[start + 2..end].trim();
+            let (mut text_end, mut next) = (start, end + 2);
+
+            // Section tags on their own line take the whole line with them
+            if tag.starts_with(['#', '^', '/']) {
+                let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+                let line_end = source[next..]
+                    .find('\n')
+                    .map_or(source.len(), |i| next + i + 1);
+                if source[line_start..start].trim().is_empty()
+                    && source[next..line_end].trim().is_empty()
+                {
+                    (text_end, next) = (line_start, line_end);
+                }
+            }
+
+            if text_end > pos {
+                nodes.push(Node::Text(source[pos..text_end].to_string()));
+            }
+            pos = next;
+
+            if let Some(name) = tag.strip_prefix(['#', '^']) {
+                let inverted = tag.starts_with('^');
+                open.push((
+                    name.trim().to_string(),
+                    inverted,
+                    std::mem::take(&mut nodes),
+                ));
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let (open_name, inverted, parent) = open
+                    .pop()
+                    .unwrap_or_else(|| panic!("Unexpected {{{{{tag}}}}} in enum template {file}"));
+                if open_name != name.trim() {
+                    panic!("{{{{{tag}}}}} closes {{{{#{open_name}}}}} in enum template {file}");
+                }
+                let section = std::mem::replace(&mut nodes, parent);
+                nodes.push(Node::Section {
+                    name: open_name,
+                    inverted,
+                    nodes: section,
+                });
+            } else {
+                nodes.push(Node::Value(tag.to_string()));
+            }
+        }
+        if let Some((name, ..)) = open.last() {
+            panic!("Unclosed {{{{#{name}}}}} in enum template {file}");
+        }
+        if pos < source.len() {
+            nodes.push(Node::Text(source[pos..].to_string()));
+        }
+
+        Self {
+            file: file.to_string(),
+            nodes,
+        }
+    }
+
+    pub fn render(&self, context: &Context) -> String {
+        let mut rendered = String::new();
+        self.render_nodes(&self.nodes, &mut vec![context], &mut rendered);
+        rendered
+    }
+
+    fn render_nodes(&self, nodes: &[Node], contexts: &mut Vec<&Context>, rendered: &mut String) {
+        for node in nodes.iter() {
+            match node {
+                Node::Text(text) => rendered.push_str(text),
+                Node::Value(name) => match self.lookup(name, contexts) {
+                    Value::Text(text) => rendered.push_str(text),
+                    _ => panic!(
+                        "{{{{{name}}}}} in enum template {} is a list or flag and should be used as a section",
+                        self.file
+                    ),
+                },
+                Node::Section {
+                    name,
+                    inverted,
+                    nodes,
+                } => match self.lookup(name, contexts) {
+                    Value::Flag(flag) => {
+                        if flag != inverted {
+                            self.render_nodes(nodes, contexts, rendered);
+                        }
+                    }
+                    Value::List(items) if *inverted => {
+                        if items.is_empty() {
+                            self.render_nodes(nodes, contexts, rendered);
+                        }
+                    }
+                    Value::List(items) => {
+                        for item in items.iter() {
+                            contexts.push(item);
+                            self.render_nodes(nodes, contexts, rendered);
+                            contexts.pop();
+                        }
+                    }
+                    Value::Text(_) => panic!(
+                        "{{{{#{name}}}}} in enum template {} is text and can't be used as a section",
+                        self.file
+                    ),
+                },
+            }
+        }
+    }
+
+    /// Finds a value in the innermost context that has it
+    fn lookup<'a>(&self, name: &str, contexts: &[&'a Context]) -> &'a Value {
+        contexts
+            .iter()
+            .rev()
+            .find_map(|context| context.get(name))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Unknown value {{{{{name}}}}} in enum template {}",
+                    self.file
+                )
+            })
+    }
+}
+
+/// The values available to enum templates:
+/// - `module`, `name` and `data`, the enum's data declaration
+/// - `first` and `last` constructors and the `count` of constructors
+/// - `values`, with the `constructor`, original `value` and `index` of each constructor,
+///   the `next` and `prev` constructors where there are any, and `is_first` and `is_last` flags
+/// - `instances`, with the `import` and `instance` of each configured extra instance,
+///   whose bodies are rendered with the other values
+pub fn enum_context(
+    module: &str,
+    name: &str,
+    data: &str,
+    values: &[String],
+    original_values: &[String],
+    instances: &[(&EnumInstance, &Template)],
+) -> Context {
+    let text = |s: &str| Value::Text(s.to_string());
+    let items = values
+        .iter()
+        .zip(original_values.iter())
+        .enumerate()
+        .map(|(i, (constructor, value))| {
+            let mut item = Context::from([
+                ("constructor", text(constructor)),
+                ("value", text(value)),
+                ("index", text(&i.to_string())),
+                ("is_first", Value::Flag(i == 0)),
+                ("is_last", Value::Flag(i == values.len() - 1)),
+            ]);
+            if let Some(next) = values.get(i + 1) {
+                item.insert("next", text(next));
+            }
+            if let Some(prev) = i.checked_sub(1).map(|prev| &values[prev]) {
+                item.insert("prev", text(prev));
+            }
+            item
+        })
+        .collect();
+    let mut context = Context::from([
+        ("module", text(module)),
+        ("name", text(name)),
+        ("data", text(data)),
+        (
+            "first",
+            text(
+                values
+                    .first()
+                    .expect("Enums should have at least one value."),
+            ),
+        ),
+        (
+            "last",
+            text(
+                values
+                    .last()
+                    .expect("Enums should have at least one value."),
+            ),
+        ),
+        ("count", text(&values.len().to_string())),
+        ("values", Value::List(items)),
+    ]);
+    let instances = instances
+        .iter()
+        .map(|(instance, body)| {
+            Context::from([
+                ("import", Value::Text(instance.import())),
+                ("instance", Value::Text(instance.instance(body, &context))),
+            ])
+        })
+        .collect();
+    context.insert("instances", Value::List(instances));
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, context: &Context) -> String {
+        Template::parse("test.template", source).render(context)
+    }
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    fn items(names: &[&str]) -> Value {
+        Value::List(
+            names
+                .iter()
+                .map(|name| Context::from([("item", text(name))]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn renders_values() {
+        let context = Context::from([("name", text("Colour")), ("first", text("Red"))]);
+        assert_eq!(
+            render("bottom = {{first}} :: {{ name }}", &context),
+            "bottom = Red :: Colour"
+        );
+    }
+
+    #[test]
+    fn repeats_list_sections_for_each_item() {
+        let context = Context::from([("items", items(&["A", "B", "C"]))]);
+        assert_eq!(
+            render("[{{#items}}{{item}};{{/items}}]", &context),
+            "[A;B;C;]"
+        );
+    }
+
+    #[test]
+    fn list_items_can_use_outer_values() {
+        let context = Context::from([("name", text("Colour")), ("items", items(&["A", "B"]))]);
+        assert_eq!(
+            render("{{#items}}{{name}}.{{item}} {{/items}}", &context),
+            "Colour.A Colour.B "
+        );
+    }
+
+    #[test]
+    fn inverted_sections_render_for_empty_lists() {
+        let source = "{{^items}}none{{/items}}";
+        assert_eq!(
+            render(source, &Context::from([("items", items(&[]))])),
+            "none"
+        );
+        assert_eq!(
+            render(source, &Context::from([("items", items(&["A"]))])),
+            ""
+        );
+    }
+
+    #[test]
+    fn flag_sections_render_when_the_flag_matches() {
+        let source = "{{#flag}}yes{{/flag}}{{^flag}}no{{/flag}}";
+        assert_eq!(
+            render(source, &Context::from([("flag", Value::Flag(true))])),
+            "yes"
+        );
+        assert_eq!(
+            render(source, &Context::from([("flag", Value::Flag(false))])),
+            "no"
+        );
+    }
+
+    #[test]
+    fn nested_sections_use_the_innermost_item() {
+        let values = Value::List(vec![
+            Context::from([("value", text("A")), ("is_last", Value::Flag(false))]),
+            Context::from([("value", text("B")), ("is_last", Value::Flag(true))]),
+        ]);
+        assert_eq!(
+            render(
+                "{{#values}}{{value}}{{^is_last}}, {{/is_last}}{{/values}}",
+                &Context::from([("values", values)])
+            ),
+            "A, B"
+        );
+    }
+
+    #[test]
+    fn strips_lines_holding_only_a_section_tag() {
+        let source = "start
+  {{#items}}
+  - {{item}}
+  {{/items}}
+end
+";
+        assert_eq!(
+            render(source, &Context::from([("items", items(&["A", "B"]))])),
+            "start\n  - A\n  - B\nend\n"
+        );
+    }
+
+    #[test]
+    fn keeps_section_tags_that_share_a_line_with_text() {
+        let source = "a {{#items}}\n{{item}}\n{{/items}} b";
+        assert_eq!(
+            render(source, &Context::from([("items", items(&["X"]))])),
+            "a \nX\n b"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown value {{missing}} in enum template test.template")]
+    fn panics_on_unknown_values() {
+        render("{{missing}}", &Context::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "{{items}} in enum template test.template is a list or flag")]
+    fn panics_on_lists_used_as_values() {
+        render("{{items}}", &Context::from([("items", items(&[]))]));
+    }
+
+    #[test]
+    #[should_panic(expected = "{{#name}} in enum template test.template is text")]
+    fn panics_on_text_used_as_a_section() {
+        render("{{#name}}{{/name}}", &Context::from([("name", text("A"))]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unclosed {{#items}} in enum template test.template")]
+    fn panics_on_unclosed_sections() {
+        Template::parse("test.template", "{{#items}}");
+    }
+
+    #[test]
+    #[should_panic(expected = "{{/other}} closes {{#items}} in enum template test.template")]
+    fn panics_on_mismatched_sections() {
+        Template::parse("test.template", "{{#items}}{{/other}}");
+    }
+
+    fn colour_context() -> Context {
+        enum_context(
+            "Schema.Enum.Colour",
+            "Colour",
+            "data Colour = Red | Green",
+            &["Red".to_string(), "Green".to_string()],
+            &["red".to_string(), "green".to_string()],
+            &[],
+        )
+    }
+
+    #[test]
+    fn enum_context_describes_each_value() {
+        assert_eq!(
+            render(
+                "{{first}}..{{last}} ({{count}}){{#values}} {{index}}:{{value}}{{/values}}",
+                &colour_context()
+            ),
+            "Red..Green (2) 0:red 1:green"
+        );
+    }
+
+    #[test]
+    fn default_templates_render() {
+        for template in [GRAPHQL_ENUM_TEMPLATE, POSTGRES_ENUM_TEMPLATE] {
+            let rendered = render(template, &colour_context());
+            assert!(rendered.starts_with("module Schema.Enum.Colour"));
+            assert!(rendered.contains("data Colour = Red | Green"));
+        }
+    }
+
+    #[test]
+    fn renders_instance_bodies_with_the_enum_values() {
+        let body = Template::parse(
+            "instance body",
+            "mkFixture = {{first}}\nall = [{{#values}}{{constructor}}{{^is_last}}, {{/is_last}}{{/values}}]",
+        );
+        let instance = EnumInstance {
+            class: "MakeFixture".to_string(),
+            module: "OaMakeFixture".to_string(),
+            package: "oa-make-fixture".to_string(),
+            graphql: Some(body.clone()),
+            postgres: None,
+        };
+        let context = enum_context(
+            "Schema.Enum.Colour",
+            "Colour",
+            "data Colour = Red | Green",
+            &["Red".to_string(), "Green".to_string()],
+            &["red".to_string(), "green".to_string()],
+            &[(&instance, &body)],
+        );
+        assert_eq!(
+            render(
+                "{{#instances}}{{import}}\n{{instance}}{{/instances}}",
+                &context
+            ),
+            "import OaMakeFixture (class MakeFixture)
+instance MakeFixture Colour where
+  mkFixture = Red
+  all = [Red, Green]"
+        );
+    }
+}
@@ -1,97 +1,242 @@
+use cynic_introspection::{Schema, Type};
 use std::{
-    collections::HashMap,
+    collections::HashSet,
     sync::{Arc, Mutex},
 };
 use stringcase::pascal_case;
 
 use crate::{
     config::parse_outside_types::{Mod, OutsideTypes},
+    enums::postgres_types::PursTypes,
     purescript_gen::{purescript_argument::Argument, purescript_import::PurescriptImport},
 };
 
+/// The object types Hasura generates for each table, which aren't tables themselves
+const GENERATED_OBJECT_SUFFIXES: [&str; 13] = [
+    "_aggregate",
+    "_aggregate_fields",
+    "_mutation_response",
+    "_avg_fields",
+    "_max_fields",
+    "_min_fields",
+    "_stddev_fields",
+    "_stddev_pop_fields",
+    "_stddev_samp_fields",
+    "_sum_fields",
+    "_var_pop_fields",
+    "_var_samp_fields",
+    "_variance_fields",
+];
+
+/// The names of the types in a role's schema that fields are resolved against
+pub struct SchemaTypes {
+    /// The tables, leaving out the root types and the objects Hasura generates for each table.
+    /// Outside type rules only apply to tables, and can check they exist.
+    pub tables: HashSet<String>,
+    /// The scalars, so Postgres array scalars can be told apart from other types
+    pub scalars: HashSet<String>,
+}
+
+impl SchemaTypes {
+    pub fn new(schema: &Schema) -> Self {
+        let root_types = [
+            Some(&schema.query_type),
+            schema.mutation_type.as_ref(),
+            schema.subscription_type.as_ref(),
+        ];
+        let mut tables = HashSet::new();
+        let mut scalars = HashSet::new();
+        for type_ in schema.types.iter() {
+            match type_ {
+                Type::Object(obj)
+                    if !obj.name.starts_with("__")
+                        && !root_types.contains(&Some(&obj.name))
+                        && !GENERATED_OBJECT_SUFFIXES
+                            .iter()
+                            .any(|suffix| obj.name.ends_with(suffix)) =>
+                {
+                    tables.insert(obj.name.clone());
+                }
+                Type::Scalar(scalar) => {
+                    scalars.insert(scalar.name.clone());
+                }
+                _ => (),
+            }
+        }
+        Self { tables, scalars }
+    }
+}
+
 pub fn as_gql_field(
     object: &str,
     field: &str,
     name: &str,
     imports: &mut Vec<PurescriptImport>,
-    purs_types: &Arc<Mutex<HashMap<String, (String, String, String)>>>,
+    purs_types: &Arc<Mutex<PursTypes>>,
     outside_types: &Arc<Mutex<OutsideTypes>>,
+    schema_types: &SchemaTypes,
 ) -> Argument {
-    let (import, type_) = outside_type(object, field, name, &purs_types, &outside_types);
-    if let Some((field_package, field_import)) = import {
-        imports.push(PurescriptImport::new(&field_import, &field_package).add_specified(&type_));
-        return Argument::new_type("AsGql")
-            .with_argument(Argument::new_type(&format!("\"{}\"", name)))
-            .with_argument(Argument::new_type(&type_));
-    }
+    let type_ = outside_type(
+        object,
+        field,
+        name,
+        imports,
+        purs_types,
+        outside_types,
+        schema_types,
+    );
     Argument::new_type("AsGql")
         .with_argument(Argument::new_type(&format!("\"{}\"", name)))
-        .with_argument(Argument::new_type(&pascal_case(&type_)))
+        .with_argument(Argument::new_type(&type_))
 }
 
-fn outside_type(
-    object: &str,
+/// Resolves the type of a field argument.
+/// Explicit argument mappings take precedence, otherwise the argument is resolved
+/// against the table that owns the field, e.g. `users_by_pk(id:)` resolves against `users.id`
+pub fn as_gql_argument(
     field: &str,
+    argument: &str,
     name: &str,
-    purs_types: &Arc<Mutex<HashMap<String, (String, String, String)>>>,
+    imports: &mut Vec<PurescriptImport>,
+    purs_types: &Arc<Mutex<PursTypes>>,
     outside_types: &Arc<Mutex<OutsideTypes>>,
-) -> (Option<(String, String)>, String) {
-    let is_comparison_fn = name.ends_with("_comparison_exp");
-
-    let mut new_object;
-    // TODO - THIS IS SLOOOOW: should check for exact matches first
-    // then process comparison expressions after
-    match MODULE_SUFFIXES.iter().find(|s| object.ends_with(**s)) {
-        None => {
-            new_object = object;
-        }
-        Some(suffix) => {
-            new_object = object
-                .strip_suffix(suffix)
-                .expect("Failed to strip suffix that was found.");
-            for prefix in MODULE_PREFIXES.iter() {
-                if new_object.starts_with(prefix) {
-                    new_object = new_object
-                        .strip_prefix(prefix)
-                        .expect("Failed to strip prefix that was found."); // TODO This needs to happen separately. Still needs to be run even without a suffix
-                }
-            }
+    schema_types: &SchemaTypes,
+) -> Argument {
+    let explicit = outside_types
+        .lock()
+        .expect("Failed to lock outside types to thread.")
+        .find_argument(field, argument, name);
+    match explicit {
+        Some(Mod {
+            package,
+            import,
+            name: type_,
+        }) => {
+            imports.push(PurescriptImport::new(&import, &package).add_specified(&type_));
+            Argument::new_type("AsGql")
+                .with_argument(Argument::new_type(&format!("\"{}\"", name)))
+                .with_argument(Argument::new_type(&type_))
         }
+        None => as_gql_field(
+            field,
+            argument,
+            name,
+            imports,
+            purs_types,
+            outside_types,
+            schema_types,
+        ),
     }
+}
 
-    if let Some((package, import, type_)) = get_outside_type(new_object, field, outside_types) {
-        if is_comparison_fn {
-            match name {
-                "String_comparison_exp" => {
-                    return (
-                        Some((
-                            "oa-ids".to_string(), // TODO this isn't in graphql-client but should be
-                            "Data.ComparisonExpString".to_string(), // There's a special case in print_module to remove the exports from this module
-                        )),
-                        format!("(ComparisonExpString {type_})"),
-                    );
-                }
-                _ => {
-                    return (
-                        Some((
-                            "graphql-client".to_string(),
-                            "GraphQL.Hasura.ComparisonExp".to_string(), // There's a special case in print_module to remove the exports from this module
-                        )),
-                        format!("(ComparisonExp {type_})"),
-                    );
-                }
-            }
+/// Resolves the purescript type for a field, adding any imports it needs.
+/// Outside types describe the element type of a field, so they compose with GraphQL list wrapping,
+/// Postgres array columns and comparison expressions.
+fn outside_type(
+    object: &str,
+    field: &str,
+    name: &str,
+    imports: &mut Vec<PurescriptImport>,
+    purs_types: &Arc<Mutex<PursTypes>>,
+    outside_types: &Arc<Mutex<OutsideTypes>>,
+    schema_types: &SchemaTypes,
+) -> String {
+    let comparison_scalar = name.strip_suffix("_comparison_exp");
+    let element =
+        postgres_array_element(comparison_scalar.unwrap_or(name), schema_types, purs_types);
+    let scalar = element.or(comparison_scalar).unwrap_or(name);
+
+    if let Some((package, import, type_)) =
+        get_outside_type(object, field, scalar, outside_types, schema_types)
+    {
+        imports.push(PurescriptImport::new(&import, &package).add_specified(&type_));
+        let type_ = match element {
+            Some(_) => format!("(Array {type_})"),
+            None => type_,
+        };
+
+        // Comparison expressions on outside types are wrapped in the configured comparison type
+        if let Some(scalar) = comparison_scalar {
+            let wrapper = outside_types
+                .lock()
+                .expect("Failed to lock outside types to thread.")
+                .find_comparison_exp(scalar);
+            imports.push(
+                PurescriptImport::new(&wrapper.import, &wrapper.package)
+                    .add_specified(&wrapper.name),
+            );
+            return format!("({} {type_})", wrapper.name);
         }
-        (Some((package, import)), type_)
+        type_
     } else if let Some((package, import, type_)) = purs_types
         .lock()
         .expect("Failed to lock purs type to thread.")
         .get(name)
     {
-        (Some((package.clone(), import.clone())), type_.clone())
+        imports.push(PurescriptImport::new(import, package).add_specified(type_));
+        type_.clone()
+    } else if let Some((package, import, type_)) = comparison_scalar
+        .is_none()
+        .then(|| prefixed_array_element(name, schema_types, purs_types))
+        .flatten()
+    {
+        imports.push(PurescriptImport::new(&import, &package).add_specified(&type_));
+        format!("(Array {type_})")
+    } else if let (Some(element), None) = (element, comparison_scalar) {
+        let element_type = outside_type(
+            object,
+            field,
+            element,
+            imports,
+            purs_types,
+            outside_types,
+            schema_types,
+        );
+        format!("(Array {element_type})")
     } else {
-        (None, base_types(name).to_string())
+        pascal_case(base_types(name))
+    }
+}
+
+/// Postgres array columns are exposed by Hasura as scalars named after their element type
+/// with an underscore prefix, e.g. `_uuid` for `uuid[]`. Returns the element type name
+/// if it is a known Postgres scalar or a generated Postgres type.
+fn postgres_array_element<'a>(
+    name: &'a str,
+    schema_types: &SchemaTypes,
+    purs_types: &Arc<Mutex<PursTypes>>,
+) -> Option<&'a str> {
+    if !schema_types.scalars.contains(name) {
+        return None;
+    }
+    let element = name
+        .strip_prefix('_')
+        .filter(|element| !element.starts_with('_'))?;
+    let is_known = base_types(element) != element
+        || purs_types
+            .lock()
+            .expect("Failed to lock purs type to thread.")
+            .contains_key(element);
+    is_known.then_some(element)
+}
+
+/// Hasura sources with a type name prefix put it before the array underscore,
+/// e.g. `analytics__status` for `status[]` in a source prefixed with `analytics_`.
+/// Returns the generated Postgres type of the element, e.g. `analytics_status`.
+fn prefixed_array_element(
+    name: &str,
+    schema_types: &SchemaTypes,
+    purs_types: &Arc<Mutex<PursTypes>>,
+) -> Option<(String, String, String)> {
+    if !schema_types.scalars.contains(name) {
+        return None;
     }
+    let (prefix, element) = name.split_once("__")?;
+    purs_types
+        .lock()
+        .expect("Failed to lock purs type to thread.")
+        .get(&format!("{prefix}_{element}"))
+        .cloned()
 }
 
 const MODULE_SUFFIXES: [&str; 7] = [
@@ -106,23 +251,48 @@ const MODULE_SUFFIXES: [&str; 7] = [
 
 const MODULE_PREFIXES: [&str; 2] = ["delete_", "update_"];
 
+/// Lists the tables an object or root field could belong to, most specific first.
+/// e.g. `delete_users_by_pk` could belong to `delete_users_by_pk`, `delete_users` or `users`
+fn table_candidates(object: &str) -> Vec<&str> {
+    let mut candidates = vec![object];
+    if let Some(table) = MODULE_SUFFIXES.iter().find_map(|s| object.strip_suffix(s)) {
+        candidates.push(table);
+    }
+    for candidate in candidates.clone() {
+        if let Some(table) = MODULE_PREFIXES
+            .iter()
+            .find_map(|p| candidate.strip_prefix(p))
+        {
+            candidates.push(table);
+        }
+    }
+    candidates
+}
+
 fn get_outside_type(
     object: &str,
     field: &str,
+    scalar: &str,
     outside_types: &Arc<Mutex<OutsideTypes>>,
+    schema_types: &SchemaTypes,
 ) -> Option<(String, String, String)> {
+    // Exact matches are checked first, so tables that happen to end with
+    // a Hasura suffix or start with a Hasura prefix still resolve to themselves
     outside_types
         .lock()
         .expect("Failed to lock outside types to thread.")
-        .get(object)
-        .map(|table| table.get(field))
-        .flatten()
+        .find(
+            &table_candidates(object),
+            field,
+            scalar,
+            &schema_types.tables,
+        )
         .map(
             |Mod {
                  package,
                  import,
                  name,
-             }| (package.to_string(), import.to_string(), name.to_string()),
+             }| (package, import, name),
         )
 }
 
@@ -138,6 +308,39 @@ pub fn base_types(type_name: &str) -> &str {
         "numeric" => "Number",
         "citext" => "String",
         "Float" => "Number",
+        // Postgres names, which are only exposed as array element types, e.g. `_text`
+        "text" | "varchar" | "bpchar" => "String",
+        "int2" | "int4" => "Int",
+        "int8" | "float4" | "float8" => "Number",
+        "bool" => "Boolean",
         _ => type_name,
     }
 }
+
+/// Whether the purescript type of a scalar, as given by `scalar_type`, has an instance of a class.
+/// The primitive types have every instance. Classes that aren't known are assumed to have one.
+pub fn scalar_has_instance(type_: &str, class: &str) -> bool {
+    match (type_, class) {
+        ("String" | "Int" | "Number" | "Boolean", _) => true,
+        ("Json", "Show") => false,
+        ("Date" | "DateTime" | "Time", "EncodeJson" | "DecodeJson") => false,
+        (_, "GqlArgString") => false,
+        _ => true,
+    }
+}
+
+/// The purescript type for a GraphQL or Postgres scalar,
+/// along with the `(module, package)` to import it from if it isn't built in.
+/// Custom scalars without a purescript type, such as `ID`, are strings in the json.
+pub fn scalar_type(scalar: &str) -> (String, Option<(&'static str, &'static str)>) {
+    let type_ = pascal_case(base_types(scalar));
+    let import = match type_.as_str() {
+        "String" | "Int" | "Number" | "Boolean" => None,
+        "Json" => Some(("Data.Argonaut.Core", "argonaut-core")),
+        "Date" => Some(("Data.Date", "datetime")),
+        "DateTime" => Some(("Data.DateTime", "datetime")),
+        "Time" => Some(("Data.Time", "datetime")),
+        _ => return ("String".to_string(), None),
+    };
+    (type_, import)
+}
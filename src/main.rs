@@ -4,20 +4,21 @@ use std::{
     thread::Result,
 };
 
-use build_schema::build_schema;
+use build_schema::{build_schema, fetch_schema};
 use config::{
+    mock_outside_types::{remove_mock_outside_types, write_mock_outside_types},
     parse_outside_types::{fetch_all_outside_types, OutsideTypes},
     parse_roles::parse_roles,
+    validate_outside_types::validate_outside_types,
     workspace::parse_workspace,
 };
 use dotenv::dotenv;
-use enums::postgres_types::fetch_types;
+use enums::{postgres_types::fetch_types, shared_enums::SharedEnums};
 use tokio::spawn;
 mod build_schema;
 mod config;
 mod enums;
 mod hasura_types;
-mod main_check_needs_migrations;
 mod purescript_gen;
 mod write;
 
@@ -30,22 +31,29 @@ async fn main() -> Result<()> {
 
     // Fetch the workspace config
     let workspace_config = parse_workspace().await?;
+    let mock_outside_types = std::env::var("MOCK_OUTSIDE_TYPES").is_ok();
 
     // Trash existing schema
-    for path in vec![
+    let generated_dirs = [
         workspace_config.postgres_enums_dir.clone(),
         workspace_config.shared_graphql_enums_dir.clone(),
         workspace_config.schema_libs_dir.clone(),
-    ]
-    .iter()
-    {
+    ];
+    for path in generated_dirs.iter() {
         remove_dir_all(path).ok();
     }
+    if mock_outside_types {
+        remove_mock_outside_types(&workspace_config);
+    }
 
     // Generate postgres enum types
-    let postgres_types = fetch_types(&workspace_config)
-        .await
-        .expect("Failed to generate postgres enum types.");
+    let (postgres_types, postgres_enums) = match fetch_types(&workspace_config).await {
+        Ok(types) => types,
+        Err(err) => {
+            eprintln!("Failed to generate Postgres types: {err}");
+            std::process::exit(1);
+        }
+    };
     let num_types = postgres_types.len();
 
     println!(
@@ -57,23 +65,57 @@ async fn main() -> Result<()> {
     let start = std::time::Instant::now();
 
     // Parse all outside type config
-    let outside_types: OutsideTypes = fetch_all_outside_types(&workspace_config);
+    let mut outside_types: OutsideTypes = fetch_all_outside_types();
+    // Mocks are written after generation, so there's nothing to validate in mocked packages yet
+    let mocks = &workspace_config.mock_outside_types;
+    let is_mocked = |package: &str| mock_outside_types && mocks.mocks(package);
+    if let (true, Some(package)) = (mock_outside_types, &mocks.package) {
+        outside_types.set_package(package, is_mocked);
+    }
+    validate_outside_types(&outside_types, &workspace_config, is_mocked);
 
     // Fetch role config
     let roles: Vec<String> = parse_roles();
     let num_roles = roles.len();
 
+    // Fetch every role's schema first, so enums can be compared between roles
+    let mut fetches = Vec::with_capacity(num_roles);
+    for role in roles.iter() {
+        fetches.push(spawn(fetch_schema(role.clone())));
+    }
+    let mut schemas = Vec::with_capacity(num_roles);
+    for fetch in fetches {
+        schemas.push(
+            fetch
+                .await
+                .expect("Failed to join schema fetch task output"),
+        );
+    }
+
+    // Shared enums are collected from every role, so they can be checked against each other
+    let shared_enums = SharedEnums::new(
+        &roles,
+        &schemas,
+        &postgres_types,
+        postgres_enums,
+        &workspace_config,
+    );
+    shared_enums.write(&workspace_config);
+
     // Postgres types are shared between all roles
     let types_ = Arc::new(Mutex::new(postgres_types));
     let outside_types = Arc::new(Mutex::new(outside_types));
+    let shared_enums = Arc::new(shared_enums);
 
     // Run schema gen for each role concurrently
     let mut tasks = Vec::with_capacity(num_roles);
-    for role in roles.iter() {
+    for (role, schema) in roles.iter().zip(schemas) {
         tasks.push(spawn(build_schema(
             role.clone(),
+            schema,
             types_.clone(),
             outside_types.clone(),
+            shared_enums.clone(),
             workspace_config.clone(),
         )));
     }
@@ -92,5 +134,27 @@ async fn main() -> Result<()> {
         start.elapsed().as_secs_f32()
     );
 
+    // Report any outside types config that didn't resolve a field in any role
+    let outside_types = outside_types
+        .lock()
+        .expect("Failed to lock outside types to thread.");
+    if mock_outside_types {
+        write_mock_outside_types(&outside_types, &workspace_config);
+    }
+    let unused = outside_types.unused();
+    if !unused.is_empty() {
+        println!("Outside types not used by any role:");
+        for location in unused.iter() {
+            println!("  {location}");
+        }
+        if std::env::var("FAIL_ON_UNUSED_OUTSIDE_TYPES").is_ok() {
+            eprintln!(
+                "Found {} unused outside types. Remove them or unset FAIL_ON_UNUSED_OUTSIDE_TYPES.",
+                unused.len()
+            );
+            std::process::exit(1);
+        }
+    }
+
     Ok(())
 }
@@ -1,110 +1,196 @@
-use std::fs;
+use std::{collections::BTreeSet, fs, process::exit};
 
 use dotenv::dotenv;
 use serde_json::Value;
-use sqlx::{postgres::PgPoolOptions, Result};
+use sqlx::postgres::PgPoolOptions;
+
+/// Every database has applied exactly the migrations on disk
+const UP_TO_DATE: i32 = 0;
+/// A database is missing migrations that are on disk
+const NEEDS_MIGRATION: i32 = 1;
+/// A database has applied migrations that aren't on disk, e.g. from another branch
+const DIVERGED: i32 = 2;
+/// A database's applied migrations couldn't be read, e.g. it couldn't be reached
+const CHECK_FAILED: i32 = 3;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     dotenv().ok();
     let db_env = std::env::var("DATABASE_URL").expect("DATABASE_URL var must be set.");
-    let pool = PgPoolOptions::new()
-        .max_connections(1)
-        .connect(&db_env)
-        .await
-        .expect("Failed to create pool for primary local database.");
-
-    let standard_result: Migrations = sqlx::query_as::<_, Migrations>(
-        r#"SELECT (cli_state->'migrations'->'default')::text as migrations from hdb_catalog.hdb_version;"#,
-    )
-    .fetch_one(&pool)
-    .await?;
-
     let test_db = std::env::var("CODEGEN_DATABASE_URL")
         .expect("CODEGEN_DATABASE_URL must be set so we can use it as a codegen base.");
     let hasura_migrations_dir =
         std::env::var("HASURA_MIGRATIONS_DIR").expect("HASURA_MIGRATIONS_DIR must be set.");
-    let test_pool = PgPoolOptions::new()
-        .max_connections(1)
-        .connect(&test_db)
-        .await
-        .expect("Failed to create codegen database pool");
 
-    let test_result: Migrations = sqlx::query_as::<_, Migrations>(
-            r#"SELECT (cli_state->'migrations'->'default')::text as migrations from hdb_catalog.hdb_version;"#,
-        )
-        .fetch_one(&test_pool)
-        .await?;
+    let files = migration_files(&hasura_migrations_dir);
+    let mut databases = vec![];
+    for (name, database_url) in [("main", &db_env), ("test", &test_db)] {
+        match applied_migrations(database_url).await {
+            Ok(applied) => databases.push((name, applied)),
+            Err(err) => {
+                eprintln!("Failed to read the migrations applied to the {name} db: {err}");
+                exit(CHECK_FAILED);
+            }
+        }
+    }
 
-    let migrations: Value = serde_json::from_str(&standard_result.migrations).expect(
-        "Failed to parse hasura migrations json. Perhaps your Hasura version is mismatched.",
-    );
-    let test_migrations: Value = serde_json::from_str(&test_result.migrations)
-        .expect("Failed to parse test migrations json.");
+    let mut exit_code = UP_TO_DATE;
+    for (name, applied) in databases.iter() {
+        let differences = Differences::new(&files, applied);
+        if !differences.missing.is_empty() {
+            println!("Migrations missing from {name} db:");
+            for version in differences.missing.iter() {
+                println!("  {version}");
+            }
+        }
+        if !differences.extra.is_empty() {
+            println!("Migrations in {name} db that aren't in {hasura_migrations_dir}:");
+            for version in differences.extra.iter() {
+                println!("  {version}");
+            }
+        }
+        exit_code = exit_code.max(differences.exit_code());
+    }
 
-    let migrations_obj = migrations
-        .as_object()
-        .expect("Failed to parse hasura migrations as an object.");
-    let test_migrations_obj = test_migrations
-        .as_object()
-        .expect("Failed to parse test migrations as an object.");
+    match exit_code {
+        UP_TO_DATE => println!("Up to date"),
+        NEEDS_MIGRATION => println!("Needs migration"),
+        _ => println!("Databases diverged"),
+    }
+    exit(exit_code);
+}
 
-    for (k, _) in test_migrations_obj.iter() {
-        if !migrations_obj.contains_key(k) {
-            println!("Migrations missing from test db");
-            return Ok(());
+/// How a database's applied migrations differ from the migrations on disk
+#[derive(Debug, PartialEq)]
+struct Differences<'a> {
+    /// Versions on disk the database hasn't applied
+    missing: Vec<&'a String>,
+    /// Versions the database has applied that aren't on disk
+    extra: Vec<&'a String>,
+}
+
+impl<'a> Differences<'a> {
+    fn new(files: &'a BTreeSet<String>, applied: &'a BTreeSet<String>) -> Self {
+        Self {
+            missing: files.difference(applied).collect(),
+            extra: applied.difference(files).collect(),
         }
     }
-    for (k, _) in migrations_obj.iter() {
-        if !test_migrations_obj.contains_key(k) {
-            println!("Migrations missing from main db");
-            return Ok(());
+
+    fn exit_code(&self) -> i32 {
+        match (self.missing.is_empty(), self.extra.is_empty()) {
+            (_, false) => DIVERGED,
+            (false, true) => NEEDS_MIGRATION,
+            (true, true) => UP_TO_DATE,
         }
     }
+}
 
-    let last_test = test_migrations_obj
-        .iter()
-        .last()
-        .expect("Must have at least one migration in test database.") // TODO make this optional?
-        .0
-        .clone();
-    let last = migrations_obj
-        .iter()
-        .last()
-        .expect("Must have at least one migration in your database.") // TODO again, make this optional?
-        .0
-        .clone();
-
-    let mut time_stamps = vec![];
+/// Reads the versions of the migrations in the Hasura migrations dir,
+/// which are the timestamps their directories start with, e.g. `1700000000000_create_users`
+fn migration_files(hasura_migrations_dir: &str) -> BTreeSet<String> {
+    let mut versions = BTreeSet::new();
     let files =
         fs::read_dir(hasura_migrations_dir).expect("The chosen Hasura directory must exist.");
     for entry in files {
         let path = entry
             .expect("Failed to parse path in Hasura directory.")
             .path();
-        let timestamp = path
+        let version = path
             .file_name()
             .expect("Failed to parse file name in Hasura dir.")
             .to_str()
             .expect("Failed to convert file name to string in Hasura dir.")
-            .split("_")
+            .split('_')
             .next()
             .expect("Failed to split timestamp out of file name in Hasura migrations dir.");
-        time_stamps.push(timestamp.to_string());
+        // Skip files that aren't migrations, e.g. `.DS_Store`
+        if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) {
+            versions.insert(version.to_string());
+        }
     }
-    time_stamps.sort();
-    let tz = time_stamps[time_stamps.len() - 1].clone();
+    versions
+}
 
-    if last_test < tz || last < tz {
-        println!("New migration exists");
-        return Ok(());
-    }
+/// Reads the versions of the migrations Hasura has applied to a database
+async fn applied_migrations(database_url: &str) -> Result<BTreeSet<String>, String> {
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(database_url)
+        .await
+        .map_err(|err| format!("Failed to connect: {err}"))?;
+
+    let result: Migrations = sqlx::query_as::<_, Migrations>(
+        r#"SELECT (cli_state->'migrations'->'default')::text as migrations from hdb_catalog.hdb_version;"#,
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| format!("Failed to query hdb_catalog.hdb_version: {err}"))?;
 
-    println!("Up to date");
-    Ok(())
+    let migrations: Value = serde_json::from_str(&result.migrations).map_err(|err| {
+        format!("Failed to parse migrations json. Perhaps your Hasura version is mismatched. {err}")
+    })?;
+    let migrations = migrations
+        .as_object()
+        .ok_or("Failed to parse migrations as an object.")?;
+    Ok(migrations.keys().cloned().collect())
 }
 
 #[derive(sqlx::Type, sqlx::FromRow, Debug)]
 struct Migrations {
     migrations: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(versions: &[&str]) -> BTreeSet<String> {
+        versions.iter().map(|version| version.to_string()).collect()
+    }
+
+    #[test]
+    fn lists_missing_and_extra_versions() {
+        let files = versions(&["1", "2", "3"]);
+        let applied = versions(&["1", "3", "4"]);
+        let differences = Differences::new(&files, &applied);
+        assert_eq!(differences.missing, ["2"]);
+        assert_eq!(differences.extra, ["4"]);
+    }
+
+    #[test]
+    fn exit_code_depends_on_the_differences() {
+        let files = versions(&["1", "2"]);
+        assert_eq!(
+            Differences::new(&files, &versions(&["1", "2"])).exit_code(),
+            UP_TO_DATE
+        );
+        assert_eq!(
+            Differences::new(&files, &versions(&["1"])).exit_code(),
+            NEEDS_MIGRATION
+        );
+        assert_eq!(
+            Differences::new(&files, &versions(&["1", "2", "3"])).exit_code(),
+            DIVERGED
+        );
+        assert_eq!(
+            Differences::new(&files, &versions(&["1", "3"])).exit_code(),
+            DIVERGED
+        );
+    }
+
+    #[test]
+    fn reads_versions_from_migration_names() {
+        let dir = std::env::temp_dir().join(format!("check_migrations_{}", std::process::id()));
+        for name in [
+            "1700000000000_create_users",
+            "1700000000001_add_posts",
+            ".DS_Store",
+        ] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        let files = migration_files(dir.to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, versions(&["1700000000000", "1700000000001"]));
+    }
+}
@@ -1,6 +1,10 @@
 pub struct Enum {
     name: String,
     values: Vec<String>,
+    /// Doc comment for the type, if any
+    doc: Option<String>,
+    /// Doc comments for each value, if any
+    docs: Vec<Option<String>>,
 }
 
 impl Enum {
@@ -8,16 +12,58 @@ impl Enum {
         Enum {
             name: name.to_string(),
             values: vec![],
+            doc: None,
+            docs: vec![],
         }
     }
 
-    pub fn with_values(&mut self, values: &Vec<String>) -> &Self {
+    pub fn with_values(&mut self, values: &Vec<String>) -> &mut Self {
         self.values = values.clone();
         self
     }
 
+    pub fn with_doc(&mut self, doc: Option<String>) -> &mut Self {
+        self.doc = doc;
+        self
+    }
+
+    pub fn with_docs(&mut self, docs: &[Option<String>]) -> &mut Self {
+        self.docs = docs.to_vec();
+        self
+    }
+
     pub fn to_string(&self) -> String {
-        let Self { name, values } = self;
-        format!("data {name}\n  = {}", values.join("\n  | "))
+        let Self {
+            name,
+            values,
+            doc,
+            docs,
+        } = self;
+        let values = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let separator = if i == 0 { "=" } else { "|" };
+                match docs.get(i) {
+                    Some(Some(doc)) => format!("{}\n  {separator} {value}", doc_comment(doc, "  ")),
+                    _ => format!("{separator} {value}"),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n  ");
+        let doc = match doc {
+            Some(doc) => format!("{}\n", doc_comment(doc, "")),
+            None => "".to_string(),
+        };
+        format!("{doc}data {name}\n  {values}")
     }
 }
+
+/// Formats a doc comment with `-- |` on every line, indenting all but the first
+fn doc_comment(doc: &str, indent: &str) -> String {
+    doc.trim()
+        .lines()
+        .map(|line| format!("-- | {line}").trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join(&format!("\n{indent}"))
+}
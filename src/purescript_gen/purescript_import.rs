@@ -34,10 +34,18 @@ impl PurescriptImport {
             {
                 let im = &import;
                 for m in merged.iter_mut() {
-                    if m.module == im.module && (m.as_name.is_none() || m.as_name == im.as_name) {
+                    if m.module == im.module && m.as_name == im.as_name {
                         m.specified.extend(im.specified.clone());
                         m.specified.sort();
                         m.specified.dedup();
+                        // Importing a type's constructors also imports the type
+                        let with_constructors: Vec<String> = m
+                            .specified
+                            .iter()
+                            .filter_map(|s| s.import.strip_suffix("(..)").map(str::to_string))
+                            .collect();
+                        m.specified
+                            .retain(|s| !with_constructors.contains(&s.import));
                         found = true;
                         break;
                     }
@@ -51,17 +59,17 @@ impl PurescriptImport {
         merged
     }
 
-    pub fn add_specified(mut self, import: &str) -> Self {
-        self.specified.push(Specified {
-            import: import.to_string(),
-        });
+    /// Imports the module qualified, e.g. `import Data.Map as Map`
+    pub fn with_as_name(mut self, as_name: &str) -> Self {
+        self.as_name = Some(as_name.to_string());
         self
     }
 
-    pub fn add_specified_mut(&mut self, import: &str) {
+    pub fn add_specified(mut self, import: &str) -> Self {
         self.specified.push(Specified {
             import: import.to_string(),
         });
+        self
     }
 
     pub fn to_string(&mut self) -> String {
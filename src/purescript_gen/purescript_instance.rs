@@ -2,18 +2,26 @@ pub struct DeriveInstance {
     class: String,
     type_name: String,
     arguments: Vec<String>,
+    newtype: bool,
 }
 
 pub fn derive_new_type_instance(for_type: &str) -> DeriveInstance {
     DeriveInstance::new(for_type, "Newtype").with_argument("_")
 }
 
+pub fn derive_newtype_instance(for_type: &str, class: &str) -> DeriveInstance {
+    let mut instance = DeriveInstance::new(for_type, class);
+    instance.newtype = true;
+    instance
+}
+
 impl DeriveInstance {
     pub fn new(name: &str, class: &str) -> Self {
         DeriveInstance {
             class: class.to_string(),
             type_name: name.to_string(),
             arguments: vec![],
+            newtype: false,
         }
     }
 
@@ -24,7 +32,8 @@ impl DeriveInstance {
 
     pub fn to_string(&self) -> String {
         format!(
-            "derive instance {} {} {}",
+            "derive {}instance {} {} {}",
+            if self.newtype { "newtype " } else { "" },
             self.class,
             self.type_name,
             self.arguments.join(" ")
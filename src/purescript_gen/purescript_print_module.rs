@@ -30,24 +30,13 @@ pub fn print_module(
         .to_string();
     let imports = PurescriptImport::merge(&imports)
         .iter_mut()
-        .map(|i| {
-            if i.module == "GraphQL.Hasura.ComparisonExp" || i.module == "Data.ComparisonExpString"
-            {
-                i.specified = vec![];
-                i.add_specified_mut(if i.module == "GraphQL.Hasura.ComparisonExp" {
-                    "ComparisonExp"
-                } else {
-                    "ComparisonExpString"
-                });
-            }
-            i.to_string()
-        })
+        .map(|i| i.to_string())
         .collect::<Vec<String>>()
         .join("\n")
         .to_string();
     let variants: String = variants
         .iter_mut()
-        .map(|v| v.to_string())
+        .map(|v| format!("{}\n\n{}", v.to_string(), v.helpers_to_string()))
         .collect::<Vec<String>>()
         .join("\n\n");
     let instances = instances
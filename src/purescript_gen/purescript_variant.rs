@@ -1,6 +1,22 @@
 pub struct Variant {
     name: String,
     values: Vec<String>,
+    /// Whether the values are also given as a named row, e.g. `UsersSelectColumnRow`
+    row: bool,
+    conversions: Vec<Conversion>,
+}
+
+/// Converts a variant to and from an enum data type that has all of its values
+struct Conversion {
+    /// The name of the conversion functions, which is the data type's name
+    /// unless it has the variant's name
+    type_name: String,
+    /// The data type's qualified name, e.g. `OaEnumsPostgres.SortOrder.SortOrder`
+    qualified_type: String,
+    /// The data type's constructor for each of the variant's values, as `(value, constructor)`
+    constructors: Vec<(String, String)>,
+    /// Whether the data type has no other values
+    total: bool,
 }
 
 impl Variant {
@@ -8,6 +24,8 @@ impl Variant {
         Variant {
             name: name.to_string(),
             values: vec![],
+            row: false,
+            conversions: vec![],
         }
     }
 
@@ -16,13 +34,167 @@ impl Variant {
         self
     }
 
+    pub fn with_row(mut self) -> Self {
+        self.row = true;
+        self
+    }
+
+    pub fn with_conversion(
+        mut self,
+        type_name: &str,
+        qualified_type: &str,
+        constructors: Vec<(String, String)>,
+        total: bool,
+    ) -> Self {
+        self.conversions.push(Conversion {
+            type_name: type_name.to_string(),
+            qualified_type: qualified_type.to_string(),
+            constructors,
+            total,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has_row(&self) -> bool {
+        self.row
+    }
+
     pub fn to_string(&self) -> String {
+        let name = &self.name;
         let values = self
             .values
             .iter()
             .map(|v| format!("\"{}\" :: Unit", v))
             .collect::<Vec<String>>()
             .join("\n  , ");
-        format!("type {} = Variant\n  ( {values}\n  )", self.name)
+        match self.row {
+            true => {
+                format!("type {name}Row =\n  ( {values}\n  )\n\ntype {name} = Variant {name}Row")
+            }
+            false => format!("type {name} = Variant\n  ( {values}\n  )"),
+        }
+    }
+
+    /// Smart constructors for each value, an array of every value
+    /// and conversions to and from the values' strings, e.g. for `OrderBy`:
+    /// `orderBy_asc`, `allOrderBy`, `orderByToString` and `orderByFromString`.
+    /// Variants with a named row also get a constructor checked against the row,
+    /// e.g. `usersSelectColumn @"id"`, and variants get conversions to and from
+    /// enum data types with all of their values, e.g. `orderByToSortOrder` and `orderByFromSortOrder`
+    pub fn helpers_to_string(&self) -> String {
+        let name = &self.name;
+        let mut chars = name.chars();
+        let prefix = match chars.next() {
+            Some(first) => first.to_lowercase().collect::<String>() + chars.as_str(),
+            None => String::new(),
+        };
+        let constructor = |value: &str| format!("{prefix}_{value}");
+
+        let constructors = self
+            .values
+            .iter()
+            .map(|v| {
+                format!(
+                    "{0} :: {name}\n{0} = inj (Proxy :: Proxy \"{v}\") unit",
+                    constructor(v)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        let all = self
+            .values
+            .iter()
+            .map(|v| constructor(v))
+            .collect::<Vec<String>>()
+            .join("\n  , ");
+        let to_string = self
+            .values
+            .iter()
+            .map(|v| format!("# on (Proxy :: Proxy \"{v}\") (\\_ -> \"{v}\")"))
+            .collect::<Vec<String>>()
+            .join("\n  ");
+        let from_string = self
+            .values
+            .iter()
+            .map(|v| format!("\"{v}\" -> Just {}", constructor(v)))
+            .collect::<Vec<String>>()
+            .join("\n  ");
+
+        let conversions: String = self
+            .conversions
+            .iter()
+            .map(|conversion| {
+                let Conversion {
+                    type_name,
+                    qualified_type,
+                    constructors,
+                    total,
+                } = conversion;
+                let to = constructors
+                    .iter()
+                    .map(|(v, c)| format!("# on (Proxy :: Proxy \"{v}\") (\\_ -> {c})"))
+                    .collect::<Vec<String>>()
+                    .join("\n  ");
+                let (from_type, from) = match total {
+                    true => (
+                        name.to_string(),
+                        constructors
+                            .iter()
+                            .map(|(v, c)| format!("{c} -> {}", constructor(v)))
+                            .collect::<Vec<String>>()
+                            .join("\n  "),
+                    ),
+                    false => (
+                        format!("Maybe {name}"),
+                        constructors
+                            .iter()
+                            .map(|(v, c)| format!("{c} -> Just {}", constructor(v)))
+                            .chain(["_ -> Nothing".to_string()])
+                            .collect::<Vec<String>>()
+                            .join("\n  "),
+                    ),
+                };
+                format!(
+                    r#"
+
+{prefix}To{type_name} :: {name} -> {qualified_type}
+{prefix}To{type_name} = case_
+  {to}
+
+{prefix}From{type_name} :: {qualified_type} -> {from_type}
+{prefix}From{type_name} = case _ of
+  {from}"#
+                )
+            })
+            .collect();
+
+        let row_constructor = match self.row {
+            true => format!(
+                "{prefix} :: forall @value rest. Cons value Unit rest {name}Row => IsSymbol value => {name}\n{prefix} = inj (Proxy :: Proxy value) unit\n\n"
+            ),
+            false => String::new(),
+        };
+
+        format!(
+            r#"{row_constructor}{constructors}
+
+all{name} :: Array {name}
+all{name} =
+  [ {all}
+  ]
+
+{prefix}ToString :: {name} -> String
+{prefix}ToString = case_
+  {to_string}
+
+{prefix}FromString :: String -> Maybe {name}
+{prefix}FromString = case _ of
+  {from_string}
+  _ -> Nothing{conversions}"#
+        )
     }
 }